@@ -0,0 +1,340 @@
+// Content-addressed archive cache: archives are stored by SHA-256 under
+// cache/archives, each with a JSON metadata file and its .minisig if it has one
+use crate::download::{get_signature_path, sha256_file};
+use crate::record::read_install_record;
+use crate::utils::{get_cache_dir, get_installed_versions, write_atomic};
+use crate::version::ZigVersion;
+use indicatif::HumanBytes;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const ARCHIVES_DIR: &str = "archives";
+const DOWNLOADS_DIR: &str = "downloads";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheEntry {
+    pub sha256: String,
+    pub file_name: String,
+    // Where the archive was first downloaded from
+    pub url: String,
+    pub size: u64,
+    pub created_at: u64,
+    pub last_used: u64,
+}
+
+// An archive in the cache along with its metadata
+#[derive(Debug, Clone)]
+pub struct CachedArchive {
+    pub path: PathBuf,
+    pub entry: CacheEntry,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn get_archives_dir(home_dir: &str) -> PathBuf {
+    get_cache_dir(home_dir).join(ARCHIVES_DIR)
+}
+
+// Where archives are downloaded to before they are added to the cache. Part
+// files stay here between runs so interrupted downloads can resume.
+pub fn get_download_path(home_dir: &str, file_name: &str) -> PathBuf {
+    get_cache_dir(home_dir).join(DOWNLOADS_DIR).join(file_name)
+}
+
+fn get_metadata_path(archive_path: &Path) -> PathBuf {
+    archive_path.with_extension("json")
+}
+
+fn read_entry(archive_path: &Path) -> Option<CacheEntry> {
+    let contents = std::fs::read_to_string(get_metadata_path(archive_path)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write_entry(archive_path: &Path, entry: &CacheEntry) -> Result<(), Box<dyn std::error::Error>> {
+    write_atomic(&get_metadata_path(archive_path), serde_json::to_string_pretty(entry)?)?;
+    Ok(())
+}
+
+// Every archive in the cache that has metadata
+pub fn list_archives(home_dir: &str) -> Vec<CachedArchive> {
+    let Ok(dir) = std::fs::read_dir(get_archives_dir(home_dir)) else {
+        return Vec::new();
+    };
+
+    dir.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_none())
+        .filter_map(|path| Some(CachedArchive { entry: read_entry(&path)?, path }))
+        .collect()
+}
+
+pub fn find_by_sha256(home_dir: &str, sha256: &str) -> Option<CachedArchive> {
+    let path = get_archives_dir(home_dir).join(sha256);
+    let entry = read_entry(&path).filter(|_| path.is_file())?;
+    Some(CachedArchive { path, entry })
+}
+
+// Find the most recently used archive downloaded from a URL, for archives
+// whose digest is not known in advance
+pub fn find_by_url(home_dir: &str, url: &str) -> Option<CachedArchive> {
+    list_archives(home_dir)
+        .into_iter()
+        .filter(|archive| archive.entry.url == url)
+        .max_by_key(|archive| archive.entry.last_used)
+}
+
+// Check a cached archive still hashes to its digest, removing it if not
+pub fn verify_archive(archive: &CachedArchive) -> bool {
+    if sha256_file(&archive.path).is_ok_and(|sha256| sha256 == archive.entry.sha256) {
+        return true;
+    }
+    println!("Warning: cached archive {} is corrupt, removing it", archive.entry.file_name);
+    remove_archive(archive);
+    false
+}
+
+// Record that a cached archive was used
+pub fn touch_archive(archive: &CachedArchive) -> Result<(), Box<dyn std::error::Error>> {
+    write_entry(&archive.path, &CacheEntry { last_used: now(), ..archive.entry.clone() })
+}
+
+pub fn remove_archive(archive: &CachedArchive) {
+    let _ = std::fs::remove_file(&archive.path);
+    let _ = std::fs::remove_file(get_signature_path(&archive.path));
+    let _ = std::fs::remove_file(get_metadata_path(&archive.path));
+}
+
+// Move a finished download, and its signature if there is one, into the cache
+pub fn store_archive(
+    home_dir: &str,
+    download_path: &Path,
+    sha256: &str,
+    url: &str,
+) -> Result<CachedArchive, Box<dyn std::error::Error>> {
+    let archives_dir = get_archives_dir(home_dir);
+    std::fs::create_dir_all(&archives_dir)?;
+
+    let path = archives_dir.join(sha256);
+    std::fs::rename(download_path, &path)?;
+    let signature_path = get_signature_path(download_path);
+    if signature_path.exists() {
+        std::fs::rename(signature_path, get_signature_path(&path))?;
+    }
+
+    let file_name = download_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let entry = CacheEntry {
+        sha256: sha256.to_string(),
+        file_name,
+        url: url.to_string(),
+        size: std::fs::metadata(&path)?.len(),
+        created_at: now(),
+        last_used: now(),
+    };
+    write_entry(&path, &entry)?;
+    Ok(CachedArchive { path, entry })
+}
+
+// Which installed versions each cached archive was installed for, Zig or ZLS
+pub fn installed_archives(home_dir: &str) -> HashMap<String, Vec<ZigVersion>> {
+    let mut archives: HashMap<String, Vec<ZigVersion>> = HashMap::new();
+    for version in get_installed_versions(home_dir) {
+        let Some(record) = read_install_record(home_dir, &version) else {
+            continue;
+        };
+        for sha256 in [record.archive_sha256, record.zls_archive_sha256].into_iter().flatten() {
+            archives.entry(sha256).or_default().push(version.clone());
+        }
+    }
+    archives
+}
+
+// Which cached archives `zpm cache clean` removes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CleanPolicy {
+    // Archives not used for longer than this
+    pub older_than: Option<Duration>,
+    // Archives no installed version was installed from
+    pub keep_installed_only: bool,
+    // Evict archives until the cache fits in this many bytes
+    pub max_size: Option<u64>,
+}
+
+impl CleanPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.older_than.is_none() && !self.keep_installed_only && self.max_size.is_none()
+    }
+}
+
+// Pick the archives a policy removes; an empty policy removes everything
+pub fn select_for_removal(home_dir: &str, policy: &CleanPolicy) -> Vec<CachedArchive> {
+    let installed = installed_archives(home_dir);
+    let now = now();
+    let (mut remove, mut keep): (Vec<_>, Vec<_>) = list_archives(home_dir).into_iter().partition(|archive| {
+        policy.is_empty()
+            || policy
+                .older_than
+                .is_some_and(|age| now.saturating_sub(archive.entry.last_used) > age.as_secs())
+            || (policy.keep_installed_only && !installed.contains_key(&archive.entry.sha256))
+    });
+
+    if let Some(max_size) = policy.max_size {
+        // Evict archives no install came from first, then the least recently used
+        keep.sort_by_key(|archive| (installed.contains_key(&archive.entry.sha256), archive.entry.last_used));
+        let mut total: u64 = keep.iter().map(|archive| archive.entry.size).sum();
+        for archive in keep {
+            if total <= max_size {
+                break;
+            }
+            total -= archive.entry.size;
+            remove.push(archive);
+        }
+    }
+
+    remove
+}
+
+// Apply the `cache_max_size` setting after an install
+pub fn enforce_size_cap(home_dir: &str, max_size: &str) {
+    let max_size = match parse_size(max_size) {
+        Ok(max_size) => max_size,
+        Err(e) => {
+            println!("Warning: ignoring cache_max_size: {}", e);
+            return;
+        }
+    };
+
+    let policy = CleanPolicy { max_size: Some(max_size), ..Default::default() };
+    let removed = select_for_removal(home_dir, &policy);
+    if removed.is_empty() {
+        return;
+    }
+    let freed: u64 = removed.iter().map(|archive| archive.entry.size).sum();
+    for archive in &removed {
+        remove_archive(archive);
+    }
+    println!(
+        "Removed {} cached archive(s) ({}) to keep the cache under {}",
+        removed.len(),
+        HumanBytes(freed),
+        HumanBytes(max_size)
+    );
+}
+
+// Parse a size such as `500M` or `2G`; suffixes are powers of 1024
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let upper = value.trim().to_ascii_uppercase();
+    let trimmed = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (number, multiplier) = match trimmed.char_indices().last() {
+        Some((i, 'K')) => (&trimmed[..i], 1u64 << 10),
+        Some((i, 'M')) => (&trimmed[..i], 1 << 20),
+        Some((i, 'G')) => (&trimmed[..i], 1 << 30),
+        Some((i, 'T')) => (&trimmed[..i], 1 << 40),
+        _ => (trimmed, 1),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid size '{}' (expected e.g. 500M or 2G)", value))?;
+    let bytes = number * multiplier as f64;
+    if !bytes.is_finite() || bytes < 0.0 || bytes >= u64::MAX as f64 {
+        return Err(format!("invalid size '{}'", value));
+    }
+    Ok(bytes as u64)
+}
+
+// Parse a duration such as `12h`, `30d` or `2w`
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let invalid = || format!("invalid duration '{}' (expected e.g. 12h, 30d or 2w)", value);
+    let split = value.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let number: u64 = value[..split].parse().map_err(|_| invalid())?;
+    let unit = match &value[split..] {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    number.checked_mul(unit).map(Duration::from_secs).ok_or_else(invalid)
+}
+
+// Time since a cached archive was last used, e.g. `3d`
+pub fn format_age(archive: &CachedArchive) -> String {
+    let age = now().saturating_sub(archive.entry.last_used);
+    match age {
+        0..60 => format!("{}s", age),
+        60..3600 => format!("{}m", age / 60),
+        3600..86400 => format!("{}h", age / 3600),
+        _ => format!("{}d", age / 86400),
+    }
+}
+
+// Files in the cache that are not tracked archives: partial downloads, and
+// archives left in the cache root by zpm versions before the archive cache
+pub fn stray_files(home_dir: &str) -> Vec<PathBuf> {
+    let cache_dir = get_cache_dir(home_dir);
+    let mut files = Vec::new();
+
+    if let Ok(dir) = std::fs::read_dir(&cache_dir) {
+        for entry in dir.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type().is_ok_and(|t| t.is_file())
+                && [".tar.xz", ".zip", ".minisig", ".part", ".part.json"].iter().any(|ext| name.ends_with(ext))
+            {
+                files.push(entry.path());
+            }
+        }
+    }
+    if let Ok(dir) = std::fs::read_dir(cache_dir.join(DOWNLOADS_DIR)) {
+        files.extend(dir.flatten().map(|entry| entry.path()));
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+        assert_eq!(parse_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_size("512mb"), Ok(512 << 20));
+        assert_eq!(parse_size("1.5GiB"), Ok(3 << 29));
+        assert_eq!(parse_size("4096"), Ok(4096));
+    }
+
+    #[test]
+    fn rejects_bad_sizes() {
+        for value in ["", "G", "2Q", "-1G", "big", "nan", "NaN", "inf", "-inf", "infinity", "1e30T"] {
+            assert!(parse_size(value).is_err(), "{:?} should not parse", value);
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("30d"), Ok(Duration::from_secs(30 * 24 * 60 * 60)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(14 * 24 * 60 * 60)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 60 * 60)));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        for value in ["", "30", "d", "30y", "-1d", "1.5d", "99999999999999999d", "18446744073709551615w"] {
+            assert!(parse_duration(value).is_err(), "{:?} should not parse", value);
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use crate::commands::exec::version_command;
+use crate::commands::install::{install, probe_dev_build, InstallOptions};
+use crate::commands::uninstall::uninstall;
+use crate::http::HttpClient;
+use crate::models::GithubCompare;
+use crate::project::get_default_version;
+use crate::utils::{file_exists, get_platform_string, get_zig_binary, get_zpm_dir};
+use crate::version::ZigVersion;
+use crate::version_req::VersionReq;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::os::unix::process::ExitStatusExt;
+
+const ZIG_COMPARE_URL: &str = "https://api.github.com/repos/ziglang/zig/compare";
+const ZIG_REPO_URL: &str = "https://github.com/ziglang/zig";
+const BISECT_INSTALLS_FILE: &str = "bisect-installs";
+const COMPARE_PAGE_SIZE: usize = 100;
+const DEFAULT_COMMAND: [&str; 3] = ["zig", "build", "test"];
+
+// Exit code a command uses to skip a build, as with `git bisect run`
+const SKIP_EXIT_CODE: i32 = 125;
+
+enum Outcome {
+    Good,
+    Bad,
+    Skip,
+}
+
+// Split a dev version into its release triple, dev number and commit
+fn split_dev_version(version: &ZigVersion) -> Option<((u64, u64, u64), u64, &str)> {
+    if !version.is_dev() {
+        return None;
+    }
+    Some((version.triple()?, version.dev_number()?, version.commit()?))
+}
+
+// Versions installed during bisect, one per line
+fn read_bisect_installs(home_dir: &str) -> Vec<ZigVersion> {
+    fs::read_to_string(get_zpm_dir(home_dir).join(BISECT_INSTALLS_FILE))
+        .map(|s| s.lines().filter_map(|l| l.trim().parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+fn record_bisect_install(home_dir: &str, version: &ZigVersion) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_zpm_dir(home_dir).join(BISECT_INSTALLS_FILE))?;
+    writeln!(file, "{}", version)?;
+    Ok(())
+}
+
+// Fetch the commits between two Zig commits, oldest first
+async fn fetch_commits(
+    client: &HttpClient,
+    good_commit: &str,
+    bad_commit: &str,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut commits = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let url = format!(
+            "{}/{}...{}?per_page={}&page={}",
+            ZIG_COMPARE_URL, good_commit, bad_commit, COMPARE_PAGE_SIZE, page
+        );
+        let response = client.get(&url).await?.error_for_status()?;
+        let compare: GithubCompare = response.json().await?;
+
+        let count = compare.commits.len();
+        commits.extend(compare.commits.into_iter().map(|c| c.sha));
+        if count < COMPARE_PAGE_SIZE || commits.len() >= compare.total_commits {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(commits)
+}
+
+// Install a build if needed and run the command against it. Not every commit
+// has a published build; those are skipped. Any other failure to install stops
+// the bisect, since skipping it could move the result.
+async fn test_build(
+    client: &HttpClient,
+    home_dir: &str,
+    version: &ZigVersion,
+    command: &[String],
+) -> Result<Outcome, Box<dyn std::error::Error>> {
+    if !file_exists(&get_zig_binary(home_dir, version)) {
+        if probe_dev_build(client, &get_platform_string()?, version).await?.is_none() {
+            println!("Skipping {}: no build was published for it", version);
+            return Ok(Outcome::Skip);
+        }
+        install(client, home_dir, Some(&VersionReq::Exact(version.clone())), &InstallOptions::default())
+            .await
+            .map_err(|e| format!("Failed to install {}: {}", version, e))?;
+        record_bisect_install(home_dir, version)?;
+    }
+
+    let (program, args) = command.split_first().ok_or("No command given")?;
+    println!("Testing {}: {}", version, command.join(" "));
+    let status = version_command(home_dir, version, program, args)?
+        .status()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    // Ctrl-C reaches the command too; stop rather than read it as a bad build
+    if status.signal() == Some(libc::SIGINT) {
+        return Err(format!("Bisect interrupted while testing {}", version).into());
+    }
+
+    Ok(match status.code() {
+        Some(0) => Outcome::Good,
+        Some(SKIP_EXIT_CODE) => Outcome::Skip,
+        _ => Outcome::Bad,
+    })
+}
+
+// Bisect nightly builds between a good and a bad version
+pub async fn bisect(
+    client: &HttpClient,
+    home_dir: &str,
+    good: &ZigVersion,
+    bad: &ZigVersion,
+    command: &[String],
+    prune: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (good_base, good_number, good_commit) =
+        split_dev_version(good).ok_or(format!("{} is not a dev version", good))?;
+    let (bad_base, bad_number, bad_commit) =
+        split_dev_version(bad).ok_or(format!("{} is not a dev version", bad))?;
+
+    if good_base != bad_base {
+        return Err(format!(
+            "Cannot bisect across development cycles ({} and {})",
+            good, bad
+        )
+        .into());
+    }
+    if bad_number <= good_number {
+        return Err(format!("Bad version {} must be newer than good version {}", bad, good).into());
+    }
+
+    let command: Vec<String> = if command.is_empty() {
+        DEFAULT_COMMAND.iter().map(|s| s.to_string()).collect()
+    } else {
+        command.to_vec()
+    };
+
+    println!("Fetching commits between {} and {}...", good_commit, bad_commit);
+    let commits = fetch_commits(client, good_commit, bad_commit).await?;
+    if commits.is_empty() {
+        return Err(format!("No commits found between {} and {}", good, bad).into());
+    }
+    // Dev numbers come from `git describe`: every commit reachable from the build
+    // but not from the release tag. The compare lists every commit reachable from
+    // `bad` but not from `good`, so the counts agree whenever `good` is an ancestor
+    // of `bad`. A mismatch means the list is incomplete or the builds are on
+    // different branches, and numbering it would name builds that don't exist.
+    if commits.len() as u64 != bad_number - good_number {
+        return Err(format!(
+            "Found {} commits between {} and {} but their dev numbers differ by {}, so builds \
+             cannot be matched to commits; try a good and bad pair closer together",
+            commits.len(),
+            good,
+            bad,
+            bad_number - good_number
+        )
+        .into());
+    }
+
+    // Each commit maps to a build; probing a build's name before installing it
+    // also confirms the number matches the commit
+    let mut candidates: Vec<ZigVersion> = commits
+        .iter()
+        .enumerate()
+        .map(|(i, sha)| {
+            ZigVersion::dev(good_base, good_number + i as u64 + 1, &sha[..sha.len().min(9)])
+        })
+        .collect();
+    if let Some(last) = candidates.last_mut() {
+        *last = bad.clone();
+    }
+
+    // Invariant: `lo` is known good (-1 is the given good version), `hi` is known bad
+    let mut lo: isize = -1;
+    let mut hi: isize = candidates.len() as isize - 1;
+    let mut skipped = HashSet::new();
+
+    loop {
+        let untested: Vec<isize> = (lo + 1..hi).filter(|i| !skipped.contains(i)).collect();
+        if untested.is_empty() {
+            break;
+        }
+
+        let mid = (lo + hi) / 2;
+        let pick = *untested
+            .iter()
+            .min_by_key(|i| (*i - mid).abs())
+            .expect("untested is not empty");
+        println!();
+        println!("Bisecting: {} builds left to test", untested.len());
+
+        match test_build(client, home_dir, &candidates[pick as usize], &command).await? {
+            Outcome::Good => lo = pick,
+            Outcome::Bad => hi = pick,
+            Outcome::Skip => {
+                skipped.insert(pick);
+            }
+        }
+    }
+
+    let last_good = if lo < 0 { good } else { &candidates[lo as usize] };
+    let first_bad = &candidates[hi as usize];
+    let last_good_commit = split_dev_version(last_good).map(|(_, _, c)| c).unwrap_or(good_commit);
+    let first_bad_commit = split_dev_version(first_bad).map(|(_, _, c)| c).unwrap_or(bad_commit);
+
+    println!();
+    println!("Last good build: {}", last_good);
+    println!("First bad build: {}", first_bad);
+    if hi - lo > 1 {
+        println!("Builds in between could not be tested");
+    }
+    println!(
+        "Commit range: {}/compare/{}...{}",
+        ZIG_REPO_URL, last_good_commit, first_bad_commit
+    );
+
+    if prune {
+        prune_bisect_installs(home_dir).await?;
+    } else if !read_bisect_installs(home_dir).is_empty() {
+        println!("Run `zpm bisect prune` to remove the builds installed during bisect");
+    }
+
+    Ok(())
+}
+
+// Uninstall the builds installed during bisect
+pub async fn prune_bisect_installs(home_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let default_version = get_default_version(home_dir);
+    let installs = read_bisect_installs(home_dir);
+
+    if installs.is_empty() {
+        println!("No bisect builds to prune");
+        return Ok(());
+    }
+
+    for version in installs {
+        if default_version.as_ref() == Some(&version) {
+            println!("Keeping {} (default version)", version);
+            continue;
+        }
+        if file_exists(&get_zig_binary(home_dir, &version)) {
+            uninstall(home_dir, &VersionReq::Exact(version)).await?;
+        }
+    }
+
+    fs::remove_file(get_zpm_dir(home_dir).join(BISECT_INSTALLS_FILE))?;
+    Ok(())
+}
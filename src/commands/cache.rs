@@ -0,0 +1,124 @@
+use crate::cache::{
+    format_age, installed_archives, list_archives, remove_archive, select_for_removal, stray_files,
+    CleanPolicy,
+};
+use crate::download::sha256_file;
+use indicatif::HumanBytes;
+use std::cmp::Reverse;
+
+fn stray_size(home_dir: &str) -> (usize, u64) {
+    let files = stray_files(home_dir);
+    let size = files
+        .iter()
+        .filter_map(|f| std::fs::metadata(f).ok())
+        .map(|m| m.len())
+        .sum();
+    (files.len(), size)
+}
+
+// List cached archives with their size, age and the versions installed from them
+pub async fn cache_ls(home_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archives = list_archives(home_dir);
+    let installed = installed_archives(home_dir);
+    archives.sort_by_key(|a| Reverse(a.entry.last_used));
+
+    if archives.is_empty() {
+        println!("No cached archives");
+    } else {
+        println!("{:<14} {:>11} {:>5}  {:<44} USED BY", "SHA-256", "SIZE", "AGE", "FILE");
+        for archive in &archives {
+            let used_by = installed
+                .get(&archive.entry.sha256)
+                .map(|versions| versions.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "))
+                .unwrap_or_else(|| "-".to_string());
+            println!(
+                "{:<14} {:>11} {:>5}  {:<44} {}",
+                &archive.entry.sha256[..archive.entry.sha256.len().min(12)],
+                HumanBytes(archive.entry.size).to_string(),
+                format_age(archive),
+                archive.entry.file_name,
+                used_by
+            );
+        }
+    }
+
+    cache_size(home_dir).await
+}
+
+// Print the total size of the cache
+pub async fn cache_size(home_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let archives = list_archives(home_dir);
+    let total: u64 = archives.iter().map(|a| a.entry.size).sum();
+    println!("{} cached archive(s), {}", archives.len(), HumanBytes(total));
+
+    let (stray_count, stray_bytes) = stray_size(home_dir);
+    if stray_count > 0 {
+        println!(
+            "{} other file(s), {}, from partial downloads or older zpm versions; `zpm cache clean` removes them",
+            stray_count,
+            HumanBytes(stray_bytes)
+        );
+    }
+    Ok(())
+}
+
+// Rehash every cached archive against its recorded digest, removing corrupt ones
+pub async fn cache_verify(home_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let archives = list_archives(home_dir);
+    let mut corrupt = 0;
+
+    for archive in &archives {
+        match sha256_file(&archive.path) {
+            Ok(sha256) if sha256 == archive.entry.sha256 => {
+                println!("{} ✓", archive.entry.file_name);
+            }
+            Ok(sha256) => {
+                println!(
+                    "{} CORRUPT: expected {}, got {}",
+                    archive.entry.file_name, archive.entry.sha256, sha256
+                );
+                remove_archive(archive);
+                corrupt += 1;
+            }
+            Err(e) => {
+                println!("{} UNREADABLE: {}", archive.entry.file_name, e);
+                remove_archive(archive);
+                corrupt += 1;
+            }
+        }
+    }
+
+    if corrupt > 0 {
+        return Err(format!(
+            "{} of {} cached archive(s) failed verification and were removed",
+            corrupt,
+            archives.len()
+        )
+        .into());
+    }
+    println!("All {} cached archive(s) verified", archives.len());
+    Ok(())
+}
+
+// Remove cached archives according to a policy; without one, empty the cache
+pub async fn cache_clean(home_dir: &str, policy: &CleanPolicy) -> Result<(), Box<dyn std::error::Error>> {
+    let removed = select_for_removal(home_dir, policy);
+    let mut freed: u64 = 0;
+
+    for archive in &removed {
+        println!("Removing {} ({})", archive.entry.file_name, HumanBytes(archive.entry.size));
+        remove_archive(archive);
+        freed += archive.entry.size;
+    }
+
+    if policy.is_empty() {
+        for file in stray_files(home_dir) {
+            freed += std::fs::metadata(&file).map(|m| m.len()).unwrap_or(0);
+            println!("Removing {}", file.display());
+            std::fs::remove_file(&file)?;
+        }
+    }
+
+    println!("Freed {}", HumanBytes(freed));
+    Ok(())
+}
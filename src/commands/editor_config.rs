@@ -0,0 +1,328 @@
+use crate::commands::zls::resolve_zig_version;
+use crate::project::{find_build_zon, find_project_version, VersionSource};
+use crate::shim::is_shim;
+use crate::utils::{
+    file_exists, find_zls_binary, get_zig_binary, get_zig_symlink, get_zls_dir, get_zls_symlink, write_atomic,
+};
+use crate::version_req::VersionReq;
+use serde_json::{json, Value};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// Editors (and ZLS itself) that `zpm editor-config` can configure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Editor {
+    VsCode,
+    Neovim,
+    Helix,
+    Zed,
+    Zls,
+}
+
+impl FromStr for Editor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "vscode" | "code" => Ok(Editor::VsCode),
+            "nvim" | "neovim" => Ok(Editor::Neovim),
+            "helix" | "hx" => Ok(Editor::Helix),
+            "zed" => Ok(Editor::Zed),
+            "zls" => Ok(Editor::Zls),
+            _ => Err(format!("Unknown editor '{}' (expected vscode, nvim, helix, zed or zls)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Editor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Editor::VsCode => write!(f, "VS Code"),
+            Editor::Neovim => write!(f, "Neovim"),
+            Editor::Helix => write!(f, "Helix"),
+            Editor::Zed => write!(f, "Zed"),
+            Editor::Zls => write!(f, "ZLS"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct EditorConfigOptions {
+    // Point at a specific version's binaries instead of ~/.local/bin
+    pub pin: bool,
+    // The version to pin; implies `pin`
+    pub zig: Option<VersionReq>,
+    // Write the project's config files instead of the user's
+    pub project: bool,
+    // Print the result instead of writing it
+    pub print: bool,
+}
+
+// The zig and zls binaries the editor is pointed at
+struct ToolPaths {
+    zig: String,
+    zls: Option<String>,
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+// The user's config directory: $XDG_CONFIG_HOME or ~/.config
+fn get_config_dir(home_dir: &str) -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(home_dir).join(".config"))
+}
+
+// Where macOS applications keep settings that live in the config directory elsewhere
+fn get_macos_dir(home_dir: &str, folder: &str) -> PathBuf {
+    Path::new(home_dir).join("Library").join(folder)
+}
+
+// The project a command runs in: where its version file or build.zig.zon is,
+// or the current directory
+fn find_project_root(cwd: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let version_file = find_project_version(cwd)?.and_then(|active| match active.source {
+        VersionSource::ZigVersionFile(path) | VersionSource::ToolVersions(path) | VersionSource::ProjectFile(path) => {
+            Some(path)
+        }
+        _ => None,
+    });
+    let marker = version_file.or_else(|| find_build_zon(cwd));
+    Ok(marker
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| cwd.to_path_buf()))
+}
+
+fn tool_paths(home_dir: &str, options: &EditorConfigOptions) -> Result<ToolPaths, Box<dyn std::error::Error>> {
+    if options.pin || options.zig.is_some() {
+        let (version, source) = resolve_zig_version(home_dir, options.zig.as_ref())?;
+        let zig_binary = get_zig_binary(home_dir, &version);
+        if !file_exists(&zig_binary) {
+            return Err(format!("Version {} is not installed", version).into());
+        }
+        eprintln!("Pinning Zig {} ({})", version, source);
+
+        let zls = find_zls_binary(&get_zls_dir(home_dir, &version)).ok();
+        if zls.is_none() {
+            eprintln!("Warning: no ZLS is installed for Zig {}; run `zpm zls install {}`", version, version);
+        }
+        return Ok(ToolPaths { zig: path_string(&zig_binary), zls: zls.as_deref().map(path_string) });
+    }
+
+    // The links in ~/.local/bin follow the default version, or each project's with shims
+    let zig_symlink = get_zig_symlink(home_dir);
+    let zls_symlink = get_zls_symlink(home_dir);
+    if !file_exists(&zig_symlink) {
+        eprintln!(
+            "Warning: {} does not exist yet; run `zpm use <version>` or `zpm shims`",
+            zig_symlink.display()
+        );
+    } else if !is_shim(&zig_symlink) {
+        eprintln!("Note: {} follows the global default; run `zpm shims` to follow each project's version", zig_symlink.display());
+    }
+    if !file_exists(&zls_symlink) {
+        eprintln!("Warning: {} does not exist yet; run `zpm zls install`", zls_symlink.display());
+    }
+    Ok(ToolPaths { zig: path_string(&zig_symlink), zls: Some(path_string(&zls_symlink)) })
+}
+
+// Set `patch`'s keys in `target`, merging nested objects and keeping every other
+// key. Returns whether anything changed.
+fn merge_json(target: &mut Value, patch: Value) -> bool {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            let mut changed = false;
+            for (key, value) in patch {
+                match target.get_mut(&key) {
+                    Some(existing) => changed |= merge_json(existing, value),
+                    None => {
+                        target.insert(key, value);
+                        changed = true;
+                    }
+                }
+            }
+            changed
+        }
+        (target, patch) if *target == patch => false,
+        (target, patch) => {
+            *target = patch;
+            true
+        }
+    }
+}
+
+// Parse a settings file that zpm can rewrite: empty, or a plain JSON object.
+// Comments and trailing commas (JSONC) can't be kept by a rewrite.
+fn parse_settings(contents: &str) -> Option<Value> {
+    if contents.trim().is_empty() {
+        return Some(json!({}));
+    }
+    serde_json::from_str::<Value>(contents).ok().filter(Value::is_object)
+}
+
+// Merge settings into a JSON settings file, creating it if needed
+fn update_json_file(path: &Path, patch: Value, print: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = if file_exists(path) { std::fs::read_to_string(path)? } else { String::new() };
+    let Some(mut settings) = parse_settings(&contents) else {
+        eprintln!("Add these settings to {} by hand:", path.display());
+        println!("{}", serde_json::to_string_pretty(&patch)?);
+        return Err(format!("{} is not a plain JSON object; it was left unchanged", path.display()).into());
+    };
+
+    let changed = merge_json(&mut settings, patch);
+    let updated = serde_json::to_string_pretty(&settings)? + "\n";
+
+    if print {
+        print!("{}", updated);
+    } else if !changed {
+        eprintln!("{} is already up to date", path.display());
+    } else {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, updated)?;
+        eprintln!("Updated {}", path.display());
+    }
+    Ok(())
+}
+
+// Quote a string for Lua and TOML snippets; both accept JSON's escapes for paths
+fn quote(value: &str) -> String {
+    Value::from(value).to_string()
+}
+
+// Write or print the configuration that points an editor at the managed toolchain.
+// Only the settings and snippets go to stdout, so they can be redirected into a
+// file; status lines go to stderr.
+pub async fn editor_config(
+    home_dir: &str,
+    editor: Editor,
+    options: &EditorConfigOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let paths = tool_paths(home_dir, options)?;
+    let project_root = if options.project {
+        let root = find_project_root(&std::env::current_dir()?)?;
+        eprintln!("Configuring {} for the project in {}", editor, root.display());
+        Some(root)
+    } else {
+        None
+    };
+    let macos = cfg!(target_os = "macos");
+
+    match editor {
+        Editor::VsCode => {
+            let path = match &project_root {
+                Some(root) => root.join(".vscode").join("settings.json"),
+                None if macos => get_macos_dir(home_dir, "Application Support").join("Code/User/settings.json"),
+                None => get_config_dir(home_dir).join("Code/User/settings.json"),
+            };
+            let mut patch = json!({ "zig.path": paths.zig });
+            if let Some(zls) = &paths.zls {
+                patch["zig.zls.enabled"] = json!("on");
+                patch["zig.zls.path"] = json!(zls);
+            }
+            update_json_file(&path, patch, options.print)?;
+        }
+        Editor::Zed => {
+            let path = match &project_root {
+                Some(root) => root.join(".zed").join("settings.json"),
+                None => get_config_dir(home_dir).join("zed").join("settings.json"),
+            };
+            let mut patch = json!({ "lsp": { "zls": { "settings": { "zig_exe_path": paths.zig } } } });
+            if let Some(zls) = &paths.zls {
+                patch["lsp"]["zls"]["binary"] = json!({ "path": zls });
+            }
+            update_json_file(&path, patch, options.print)?;
+        }
+        Editor::Zls => {
+            let path = match &project_root {
+                Some(root) => root.join("zls.json"),
+                None if macos => get_macos_dir(home_dir, "Preferences").join("zls.json"),
+                None => get_config_dir(home_dir).join("zls.json"),
+            };
+            update_json_file(&path, json!({ "zig_exe_path": paths.zig }), options.print)?;
+        }
+        // Lua and TOML configs are printed rather than rewritten
+        Editor::Neovim => {
+            let zls = paths.zls.as_deref().ok_or("Neovim needs a ZLS binary to start")?;
+            match &project_root {
+                Some(root) => eprintln!("Add this to {} (with 'exrc' enabled):", root.join(".nvim.lua").display()),
+                None => eprintln!("Add this to your Neovim config (init.lua, Neovim 0.11+):"),
+            }
+            eprintln!();
+            println!("vim.lsp.config(\"zls\", {{");
+            println!("  cmd = {{ {} }},", quote(zls));
+            println!("  settings = {{ zls = {{ zig_exe_path = {} }} }},", quote(&paths.zig));
+            println!("}})");
+            println!("vim.lsp.enable(\"zls\")");
+        }
+        Editor::Helix => {
+            let zls = paths.zls.as_deref().ok_or("Helix needs a ZLS binary to start")?;
+            let path = match &project_root {
+                Some(root) => root.join(".helix").join("languages.toml"),
+                None => get_config_dir(home_dir).join("helix").join("languages.toml"),
+            };
+            eprintln!("Add this to {}:", path.display());
+            eprintln!();
+            println!("[language-server.zls]");
+            println!("command = {}", quote(zls));
+            println!("config = {{ zig_exe_path = {} }}", quote(&paths.zig));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_into_existing_settings() {
+        let mut settings = json!({
+            "editor.fontSize": 14,
+            "lsp": { "zls": { "settings": { "enable_snippets": true } } },
+        });
+        let patch = json!({
+            "zig.path": "/home/me/.local/bin/zig",
+            "lsp": { "zls": { "settings": { "zig_exe_path": "/home/me/.local/bin/zig" } } },
+        });
+        assert!(merge_json(&mut settings, patch.clone()));
+        assert_eq!(
+            settings,
+            json!({
+                "editor.fontSize": 14,
+                "lsp": { "zls": { "settings": {
+                    "enable_snippets": true,
+                    "zig_exe_path": "/home/me/.local/bin/zig",
+                } } },
+                "zig.path": "/home/me/.local/bin/zig",
+            })
+        );
+
+        // Merging the same settings again changes nothing
+        assert!(!merge_json(&mut settings, patch));
+        assert!(merge_json(&mut settings, json!({ "zig.path": "/opt/zig" })));
+        assert_eq!(settings["zig.path"], "/opt/zig");
+    }
+
+    #[test]
+    fn only_plain_json_objects_are_rewritten() {
+        assert_eq!(parse_settings(""), Some(json!({})));
+        assert_eq!(parse_settings(" \n"), Some(json!({})));
+        assert_eq!(parse_settings(r#"{ "a": 1 }"#), Some(json!({ "a": 1 })));
+        for contents in [
+            "[1, 2]",
+            "\"zig\"",
+            "{ // comment\n \"a\": 1 }",
+            r#"{ "a": 1, }"#,
+            "{",
+        ] {
+            assert_eq!(parse_settings(contents), None, "{:?} should be left alone", contents);
+        }
+    }
+}
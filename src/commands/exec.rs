@@ -0,0 +1,91 @@
+use crate::commands::install::{install, InstallOptions};
+use crate::http::HttpClient;
+use crate::lock::lock_zpm;
+use crate::project::VERSION_ENV;
+use crate::utils::{
+    file_exists, find_zls_binary, get_version_dir, get_zig_binary, get_zls_dir,
+};
+use crate::version::ZigVersion;
+use crate::version_req::VersionReq;
+use std::env;
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+
+// Build a command that runs under a specific installed Zig version
+pub fn version_command(
+    home_dir: &str,
+    version: &ZigVersion,
+    program: &str,
+    args: &[String],
+) -> Result<Command, Box<dyn std::error::Error>> {
+    let zig_binary = get_zig_binary(home_dir, version);
+    if !file_exists(&zig_binary) {
+        return Err(format!("Version {} is not installed", version).into());
+    }
+
+    // Put the version directory (and its ZLS, if any) ahead of everything else
+    let mut paths = vec![get_version_dir(home_dir, version)];
+    if let Some(zls_bin_dir) = find_zls_binary(&get_zls_dir(home_dir, version))
+        .ok()
+        .and_then(|zls| zls.parent().map(|p| p.to_path_buf()))
+    {
+        paths.push(zls_bin_dir);
+    }
+    if let Some(path) = env::var_os("PATH") {
+        paths.extend(env::split_paths(&path));
+    }
+
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .env("PATH", env::join_paths(paths)?)
+        .env("ZIG", &zig_binary)
+        .env(VERSION_ENV, version.to_string());
+    Ok(command)
+}
+
+// Run a command under a specific Zig version, returning its exit code
+pub async fn exec(
+    client: &HttpClient,
+    home_dir: &str,
+    requirement: &VersionReq,
+    install_missing: bool,
+    offline: bool,
+    command: &[String],
+) -> Result<i32, Box<dyn std::error::Error>> {
+    let (program, args) = command.split_first().ok_or("No command given")?;
+
+    // Keep the command's stdout to itself
+    let target_version = match requirement.resolve_installed(home_dir) {
+        Some(v) => {
+            eprintln!("Resolved {} to {}", requirement, v);
+            v
+        }
+        None if install_missing => {
+            let options = InstallOptions {
+                offline,
+                progress_to_stderr: true,
+                ..Default::default()
+            };
+            let _lock = lock_zpm(home_dir)?;
+            install(client, home_dir, Some(requirement), &options).await?
+        }
+        None => {
+            return Err(format!(
+                "No installed version matches {}. Run `zpm install {}` or pass --install.",
+                requirement, requirement
+            )
+            .into());
+        }
+    };
+
+    let status = version_command(home_dir, &target_version, program, args)?
+        .status()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    // Mirror the shell convention for children killed by a signal
+    Ok(status
+        .code()
+        .or_else(|| status.signal().map(|s| 128 + s))
+        .unwrap_or(1))
+}
@@ -1,78 +1,252 @@
-use crate::download::{download_file, extract_tarball, verify_checksum};
+use crate::cache::{
+    enforce_size_cap, find_by_sha256, find_by_url, get_download_path, store_archive, touch_archive,
+    verify_archive,
+};
+use crate::config::load_config;
+use crate::download::{
+    feed_file, get_signature_path, url_file_name, verify_file_signature,
+};
+use crate::http::HttpClient;
+use crate::index::fetch_index;
+use crate::interrupt::catch_interrupts;
+use crate::mirrors::{download_with_mirrors, load_mirrors};
 use crate::models::{PlatformEntry, ReleaseIndex, VersionEntry};
+use crate::pipeline::ExtractPipeline;
+use crate::project::{find_build_zon, find_project_version, get_default_version, read_minimum_zig_version};
+use crate::record::{write_install_record, InstallRecord};
 use crate::utils::{
-    file_exists, get_cache_dir, get_platform_string, get_version_dir, get_zig_binary,
-    version_compare,
+    clean_stale_staging, file_exists, get_platform_string, get_staging_dir,
+    get_version_dir, get_zig_binary, redirect_stdout_to_stderr, set_master_alias,
 };
-use indicatif::ProgressBar;
-use reqwest::Client;
-use tokio::fs::create_dir_all;
+use crate::version::ZigVersion;
+use crate::version_req::VersionReq;
+use indicatif::HumanBytes;
+use reqwest::StatusCode;
+use std::path::Path;
+use tokio::process::Command;
 
-const INDEX_URL: &str = "https://ziglang.org/download/index.json";
+const BUILDS_URL: &str = "https://ziglang.org/builds";
 
-// Fetch the list of available releases
-async fn fetch_releases(client: &Client) -> Result<ReleaseIndex, Box<dyn std::error::Error>> {
-    let response = client.get(INDEX_URL).send().await?;
-    let releases: ReleaseIndex = response.json().await?;
-    Ok(releases)
+// How an install behaves beyond which version it installs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InstallOptions {
+    pub set_as_default: bool,
+    // Resolve from the cached index and install only from cached archives
+    pub offline: bool,
+    // Install without checking the archive's minisign signature
+    pub skip_signature: bool,
+    // Print progress to stderr, keeping stdout for the command run afterwards
+    pub progress_to_stderr: bool,
 }
 
-// Get the version entry for a specific version
-fn get_version_entry<'a>(
-    releases: &'a ReleaseIndex,
-    version: &str,
-) -> Result<(String, &'a VersionEntry), Box<dyn std::error::Error>> {
-    // Determine the exact version to install
-    let target_version = match version {
-        "latest" | "master" => "master".to_string(),
-        "stable" => {
-            // Find the latest stable version (non-master)
-            let mut versions: Vec<String> = releases
-                .versions
-                .keys()
-                .filter(|k| *k != "master")
-                .cloned()
-                .collect();
-            versions.sort_by(|a, b| version_compare(a, b));
-            versions.first().unwrap_or(&"master".to_string()).clone()
+// Candidate archive URLs for a dev build. The file naming switched from
+// os-arch to arch-os during the 0.14 cycle, so both are tried.
+fn dev_build_urls(platform: &str, version: &ZigVersion) -> Vec<String> {
+    let (arch, os) = platform.split_once('-').unwrap_or((platform, ""));
+    vec![
+        format!("{}/zig-{}-{}-{}.tar.xz", BUILDS_URL, arch, os, version),
+        format!("{}/zig-{}-{}-{}.tar.xz", BUILDS_URL, os, arch, version),
+    ]
+}
+
+// Probe the builds archive for a dev build's detached signature. Returns the
+// archive URL, or None when no build was published under that name; any other
+// failure is an error.
+pub async fn probe_dev_build(
+    client: &HttpClient,
+    platform: &str,
+    version: &ZigVersion,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    for url in dev_build_urls(platform, version) {
+        let signature_url = format!("{}.minisig", url);
+        let status = client.get(&signature_url).await?.status();
+        if status.is_success() {
+            return Ok(Some(url));
         }
-        v => v.to_string(),
+        if status != StatusCode::NOT_FOUND {
+            return Err(format!("Request to {} failed: HTTP {}", signature_url, status).into());
+        }
+    }
+    Ok(None)
+}
+
+// Locate a dev build in the builds archive
+async fn find_dev_build(
+    client: &HttpClient,
+    platform: &str,
+    version: &ZigVersion,
+) -> Result<String, Box<dyn std::error::Error>> {
+    probe_dev_build(client, platform, version)
+        .await?
+        .ok_or_else(|| format!("No build of Zig {} found for platform {}", version, platform).into())
+}
+
+// Get the (major, minor) series of a version
+fn minor_series(version: &ZigVersion) -> Option<(u64, u64)> {
+    version.triple().map(|(major, minor, _)| (major, minor))
+}
+
+// Pick a release satisfying a minimum version: the newest patch release of the
+// same minor series if there is one, otherwise the oldest newer release. A dev
+// minimum falls back to master when no release has caught up with it yet.
+fn find_minimum_match(
+    releases: &ReleaseIndex,
+    minimum: &ZigVersion,
+) -> Result<ZigVersion, Box<dyn std::error::Error>> {
+    let mut candidates: Vec<ZigVersion> = releases
+        .releases()
+        .map(|(v, _)| v)
+        .filter(|v| v.is_stable() && v >= minimum)
+        .collect();
+    candidates.sort();
+
+    let series = minor_series(minimum);
+    if let Some(release) = candidates.iter().rev().find(|v| minor_series(v) == series).or(candidates.first()) {
+        return Ok(release.clone());
+    }
+
+    match releases.master_version() {
+        Some(master) if !minimum.is_stable() && &master >= minimum => Ok(ZigVersion::Master),
+        _ => Err(format!("No release satisfies minimum Zig version {}", minimum).into()),
+    }
+}
+
+// Warn when the global default is older than a project's minimum version
+fn warn_if_default_outdated(home_dir: &str, minimum: &ZigVersion) {
+    let Some(default) = get_default_version(home_dir) else {
+        return;
     };
 
+    if &default < minimum {
+        println!(
+            "Warning: default Zig version {} is older than the project's minimum_zig_version {}",
+            default, minimum
+        );
+    }
+}
+
+// Get the version entry for a resolved version. Nightlies resolve to their
+// concrete dev version; the returned flag is set for the index's master entry.
+fn get_version_entry<'a>(
+    releases: &'a ReleaseIndex,
+    target_version: &ZigVersion,
+) -> Result<(ZigVersion, bool, &'a VersionEntry), Box<dyn std::error::Error>> {
+    // An exact nightly version matches master when it is the current one
+    let master_version = releases.master_version();
+    if target_version.is_master() || master_version.as_ref() == Some(target_version) {
+        let master_entry = releases
+            .versions
+            .get("master")
+            .ok_or("Master version not found in index")?;
+        let nightly_version = master_version.ok_or("Master entry in index has no version")?;
+        return Ok((nightly_version, true, master_entry));
+    }
+
     // Get the version entry
     let version_entry = releases
         .versions
-        .get(&target_version)
+        .get(&target_version.to_string())
         .ok_or(format!("Version {} not found", target_version))?;
 
-    Ok((target_version, version_entry))
+    Ok((target_version.clone(), false, version_entry))
+}
+
+// Run `zig version` from an unpacked tree and check it reports the expected version
+async fn validate_install(dir: &Path, expected: &ZigVersion) -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::new(dir.join("zig"))
+        .arg("version")
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run the unpacked zig binary: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "`zig version` failed for the unpacked archive: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    let reported = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if reported != expected.to_string() {
+        return Err(format!("Unpacked zig reports version {}, expected {}", reported, expected).into());
+    }
+    Ok(())
 }
 
-// Install a Zig version
+// Install a Zig version, returning the version that was installed. Ctrl-C
+// cancels the install, discarding the staged tree and any part file that
+// cannot be resumed. Ctrl-C is only caught while the install runs.
 pub async fn install(
-    client: &Client,
+    client: &HttpClient,
     home_dir: &str,
-    version: &str,
-    set_as_default: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Installing Zig version: {}", version);
+    requirement: Option<&VersionReq>,
+    options: &InstallOptions,
+) -> Result<ZigVersion, Box<dyn std::error::Error>> {
+    let _stdout = if options.progress_to_stderr { Some(redirect_stdout_to_stderr()?) } else { None };
+    let interrupts = catch_interrupts();
+    tokio::select! {
+        result = install_version(client, home_dir, requirement, options) => result,
+        _ = interrupts.interrupted() => Err("Install interrupted; partial files were cleaned up".into()),
+    }
+}
+
+async fn install_version(
+    client: &HttpClient,
+    home_dir: &str,
+    requirement: Option<&VersionReq>,
+    options: &InstallOptions,
+) -> Result<ZigVersion, Box<dyn std::error::Error>> {
+    let InstallOptions { set_as_default, offline, skip_signature, .. } = *options;
+    let config = load_config(home_dir)?;
+    let releases = fetch_index(client, home_dir, offline).await?;
+
+    // Without an explicit version, use the project's pinned or minimum version
+    let cwd = std::env::current_dir()?;
+    let mut minimum_version = None;
+    let requirement = match requirement {
+        Some(r) => r.clone(),
+        None => {
+            if let Some(active) = find_project_version(&cwd)? {
+                println!("Using Zig version {} ({})", active.version, active.source);
+                VersionReq::Exact(active.version)
+            } else if let Some(zon) = find_build_zon(&cwd)
+                && let Some(minimum) = read_minimum_zig_version(&zon)?
+            {
+                println!("Project requires Zig >= {} ({})", minimum, zon.display());
+                let version = find_minimum_match(&releases, &minimum)?;
+                minimum_version = Some(minimum);
+                VersionReq::Exact(version)
+            } else {
+                VersionReq::LatestStable
+            }
+        }
+    };
 
-    let releases = fetch_releases(client).await?;
-    let (target_version, version_entry) = get_version_entry(&releases, version)?;
+    let version = requirement.resolve_index(&releases)?;
 
     // Get platform string (e.g., x86_64-macos)
     let platform = get_platform_string()?;
     println!("Platform: {}", platform);
 
-    // Parse platform entries from other_fields
-    let platform_entry = version_entry
-        .other_fields
-        .get(&platform)
-        .and_then(|v| serde_json::from_value::<PlatformEntry>(v.clone()).ok())
-        .ok_or(format!(
-            "Platform {} not supported for version {}",
-            platform, target_version
-        ))?;
+    // Dev builds that have dropped out of the index are fetched from the builds archive
+    let (target_version, is_master, platform_entry) = match get_version_entry(&releases, &version) {
+        Ok((target_version, is_master, version_entry)) => {
+            // Parse platform entries from other_fields
+            let platform_entry = version_entry
+                .other_fields
+                .get(&platform)
+                .and_then(|v| serde_json::from_value::<PlatformEntry>(v.clone()).ok())
+                .ok_or(format!(
+                    "Platform {} not supported for version {}",
+                    platform, target_version
+                ))?;
+            (target_version, is_master, Some(platform_entry))
+        }
+        Err(_) if version.is_dev() => (version, false, None),
+        Err(e) => return Err(e),
+    };
+    println!("Resolved {} to {}", requirement, target_version);
 
     // Check if version is already installed
     let version_dir = get_version_dir(home_dir, &target_version);
@@ -80,37 +254,164 @@ pub async fn install(
 
     if file_exists(&zig_binary) {
         println!("Zig version {} is already installed", target_version);
+        if is_master {
+            set_master_alias(home_dir, &target_version)?;
+        }
         if set_as_default {
             crate::commands::use_cmd::set_default(home_dir, &target_version).await?;
         }
-        return Ok(());
-    }
-
-    // Download and install
-    let cache_dir = get_cache_dir(home_dir);
-    let archive_filename = format!("zig-{}-{}.tar.xz", platform, target_version);
-    let archive_path = cache_dir.join(archive_filename);
-
-    // Download the tarball
-    println!("Downloading {}...", platform_entry.tarball);
-    download_file(client, &platform_entry.tarball, &archive_path).await?;
-
-    // Verify checksum
-    let pb = ProgressBar::new_spinner();
-    pb.enable_steady_tick(std::time::Duration::from_millis(50)); // Set faster tick rate
-    pb.set_message("Verifying checksum...");
-    pb.tick();
-    verify_checksum(&archive_path, &platform_entry.shasum)?;
-    pb.finish_with_message("Checksum verified ✓");
-
-    // Extract the tarball
-    let pb = ProgressBar::new_spinner();
-    pb.enable_steady_tick(std::time::Duration::from_millis(50)); // Set faster tick rate
-    pb.set_message("Extracting archive...");
-    pb.tick();
-    create_dir_all(&version_dir).await?;
-    extract_tarball(&archive_path, &version_dir)?;
-    pb.finish_with_message("Archive extracted ✓");
+        if let Some(minimum) = &minimum_version {
+            warn_if_default_outdated(home_dir, minimum);
+        }
+        return Ok(target_version);
+    }
+
+    // Index entries also carry a checksum; archived dev builds only have a
+    // signature, so skipping it would leave nothing to verify. A dev build downloaded before is found in the cache by URL
+    // without probing the builds archive.
+    if skip_signature && platform_entry.is_none() {
+        return Err(format!(
+            "Version {} has no checksum in the release index, so its signature cannot be skipped",
+            target_version
+        )
+        .into());
+    }
+    let (tarball_url, shasum) = match platform_entry {
+        Some(entry) => (entry.tarball, Some(entry.shasum)),
+        None => match dev_build_urls(&platform, &target_version)
+            .into_iter()
+            .find(|url| find_by_url(home_dir, url).is_some())
+        {
+            Some(url) => (url, None),
+            None if offline => {
+                return Err(format!(
+                    "Version {} is not in the cached release index or the archive cache and cannot be fetched offline",
+                    target_version
+                )
+                .into());
+            }
+            None => {
+                println!(
+                    "Version {} is not in the release index, looking in the builds archive...",
+                    target_version
+                );
+                (find_dev_build(client, &platform, &target_version).await?, None)
+            }
+        },
+    };
+
+    let public_key = if skip_signature {
+        println!();
+        println!("WARNING: --skip-signature given. The archive's minisign signature will NOT be checked.");
+        println!("WARNING: only the checksum from the release index protects this install.");
+        println!();
+        None
+    } else {
+        Some(config.public_key.as_str())
+    };
+
+    // Only a finished install is ever moved into place, so anything already at
+    // the version directory was left by an older zpm and is incomplete
+    if file_exists(&version_dir) {
+        println!("Removing incomplete install at {}", version_dir.display());
+        std::fs::remove_dir_all(&version_dir)?;
+    }
+    clean_stale_staging(home_dir)?;
+
+    // The archive is hashed and unpacked into a staging directory as it is
+    // read, dropping its top-level directory. Nothing is moved into place
+    // until the signature, checksum and `zig version` have all been checked.
+    let mut pipeline = ExtractPipeline::new(get_staging_dir(home_dir, &target_version), 1);
+
+    // Archives are cached by SHA-256. Release archives are looked up by their
+    // checksum; archived dev builds, which have none, by URL. A cached archive
+    // that still hashes correctly is used without touching the network.
+    let cached = match &shasum {
+        Some(shasum) => find_by_sha256(home_dir, shasum),
+        None => find_by_url(home_dir, &tarball_url),
+    }
+    .filter(|archive| public_key.is_none() || file_exists(&get_signature_path(&archive.path)))
+    .filter(verify_archive);
+    let download_path = get_download_path(home_dir, url_file_name(&tarball_url)?);
+
+    // Verify the cached archive or download and verify the tarball
+    let source = if let Some(archive) = &cached {
+        println!("Using cached archive {} ({})", archive.entry.file_name, archive.entry.sha256);
+        match public_key {
+            Some(public_key) => {
+                let signature = std::fs::read_to_string(get_signature_path(&archive.path))?;
+                verify_file_signature(
+                    &archive.path,
+                    &signature,
+                    public_key,
+                    url_file_name(&tarball_url)?,
+                    &mut pipeline,
+                )
+                .await?;
+            }
+            None => feed_file(&archive.path, &mut pipeline).await?,
+        }
+        touch_archive(archive)?;
+        archive.entry.url.clone()
+    } else if offline {
+        return Err(format!(
+            "Zig {} is not installed and its archive is not cached; cannot download in offline mode",
+            target_version
+        )
+        .into());
+    } else {
+        if let Some(parent) = download_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mirrors = load_mirrors(client, home_dir, &config).await;
+        download_with_mirrors(client, &mirrors, &tarball_url, &download_path, public_key, &mut pipeline).await?
+    };
+
+    // Finished first so the extraction spinner is gone before anything is printed
+    let (actual_shasum, entries, bytes) = pipeline.finish()?;
+    if public_key.is_some() {
+        println!("Signature verified ✓");
+    }
+    if let Some(shasum) = &shasum {
+        if &actual_shasum != shasum {
+            if cached.is_none() {
+                let _ = std::fs::remove_file(&download_path);
+                let _ = std::fs::remove_file(get_signature_path(&download_path));
+            }
+            return Err(format!("Checksum mismatch: expected {}, got {}", shasum, actual_shasum).into());
+        }
+        println!("Checksum verified ✓");
+    }
+    if cached.is_none() {
+        store_archive(home_dir, &download_path, &actual_shasum, &tarball_url)?;
+    }
+
+    println!("Extracted {} entries ({})", entries, HumanBytes(bytes));
+
+    validate_install(pipeline.staging_dir(), &target_version).await?;
+    pipeline.commit(&version_dir)?;
+    write_install_record(
+        home_dir,
+        &target_version,
+        &InstallRecord {
+            source: Some(source),
+            archive_sha256: Some(actual_shasum),
+            ..Default::default()
+        },
+    )?;
+
+    // Trim the cache once the new archive counts as belonging to an install
+    if cached.is_none()
+        && let Some(max_size) = &config.cache_max_size
+    {
+        enforce_size_cap(home_dir, max_size);
+    }
+
+    // Point the master alias at the newly installed nightly
+    if is_master {
+        set_master_alias(home_dir, &target_version)?;
+        println!("master -> {}", target_version);
+    }
 
     // Set as default if requested
     if set_as_default {
@@ -118,5 +419,49 @@ pub async fn install(
     }
 
     println!("Successfully installed Zig version {}", target_version);
-    Ok(())
+    if let Some(minimum) = &minimum_version {
+        warn_if_default_outdated(home_dir, minimum);
+    }
+    Ok(target_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(versions: &[&str], master: &str) -> ReleaseIndex {
+        let mut entries: serde_json::Map<String, serde_json::Value> =
+            versions.iter().map(|v| (v.to_string(), serde_json::json!({}))).collect();
+        entries.insert("master".to_string(), serde_json::json!({ "version": master }));
+        serde_json::from_value(serde_json::Value::Object(entries)).unwrap()
+    }
+
+    fn v(s: &str) -> ZigVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn dev_minimum_prefers_a_release_that_satisfies_it() {
+        let releases = index(&["0.13.0", "0.14.0", "0.14.1", "0.15.1"], "0.16.0-dev.200+abcdef123");
+        let minimum = v("0.14.0-dev.3451+d8d2aa9af");
+        assert_eq!(find_minimum_match(&releases, &minimum).unwrap(), v("0.14.1"));
+    }
+
+    #[test]
+    fn dev_minimum_falls_back_to_master() {
+        let releases = index(&["0.13.0"], "0.14.0-dev.3500+abcdef123");
+        let minimum = v("0.14.0-dev.3451+d8d2aa9af");
+        assert_eq!(find_minimum_match(&releases, &minimum).unwrap(), ZigVersion::Master);
+
+        let releases = index(&["0.13.0"], "0.14.0-dev.3000+abcdef123");
+        assert!(find_minimum_match(&releases, &minimum).is_err());
+    }
+
+    #[test]
+    fn stable_minimum_picks_the_newest_patch_of_its_series() {
+        let releases = index(&["0.12.0", "0.12.1", "0.13.0"], "0.14.0-dev.1+abcdef123");
+        assert_eq!(find_minimum_match(&releases, &v("0.12.0")).unwrap(), v("0.12.1"));
+        assert_eq!(find_minimum_match(&releases, &v("0.12.2")).unwrap(), v("0.13.0"));
+        assert!(find_minimum_match(&releases, &v("0.14.0")).is_err());
+    }
 }
@@ -1,113 +1,323 @@
-use crate::download::{download_file, extract_tarball, extract_zip};
-use crate::models::ZlsRelease;
-use crate::utils::{file_exists, get_cache_dir, get_current_file, get_local_bin_dir, get_platform_string, get_version_dir};
-use reqwest::Client;
-use std::fs;
-use std::os::unix::fs::symlink;
-use std::path::Path;
-use tokio::fs::create_dir_all;
-
-// ZLS repository release URL
-const ZLS_RELEASES_URL: &str = "https://api.github.com/repos/zigtools/zls/releases/latest";
-
-// Find the ZLS binary in the extracted directory
-fn find_zls_binary(zls_dir: &Path) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
-    // Look for the zls binary in the extracted directory
-    let mut queue = vec![zls_dir.to_path_buf()];
-    
-    while let Some(dir) = queue.pop() {
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                
-                if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                    queue.push(path);
-                } else if entry.file_name() == "zls" {
-                    return Ok(path);
-                }
-            }
+use crate::cache::{
+    enforce_size_cap, find_by_sha256, find_by_url, get_download_path, store_archive, touch_archive,
+    verify_archive,
+};
+use crate::config::load_config;
+use crate::download::{
+    download_verified, get_signature_path, sha256_file, url_file_name, verify_file_signature,
+};
+use crate::extract::{extract_tarball, extract_zip};
+use crate::http::HttpClient;
+use crate::models::{PlatformEntry, ZlsRelease, ZlsSelectResponse};
+use crate::commands::use_cmd::link_zls;
+use crate::commands::zls::resolve_zig_version;
+use crate::project::is_default_version;
+use crate::record::{read_install_record, write_install_record};
+use crate::utils::{file_exists, find_zls_binary, get_platform_string, get_zls_dir};
+use crate::version::ZigVersion;
+use crate::version_req::VersionReq;
+use reqwest::Url;
+use tokio::fs::{create_dir_all, remove_dir_all};
+
+// Tagged ZLS releases, used when the release service has no answer
+const ZLS_GITHUB_RELEASES_URL: &str = "https://api.github.com/repos/zigtools/zls/releases";
+
+// A ZLS build picked for a Zig version, with what its integrity is checked against
+struct ZlsBuild {
+    version: String,
+    url: String,
+    file_name: String,
+    shasum: Option<String>,
+    // A .minisig is published next to the archive
+    signed: bool,
+}
+
+// Ask the zigtools release service for the ZLS build matching a Zig version
+async fn select_zls_build(
+    client: &HttpClient,
+    base_url: &str,
+    zig_version: &ZigVersion,
+    platform: &str,
+) -> Result<ZlsBuild, Box<dyn std::error::Error>> {
+    let url = Url::parse_with_params(
+        &format!("{}/v1/zls/select-version", base_url.trim_end_matches('/')),
+        &[("zig_version", zig_version.to_string().as_str()), ("compatibility", "only-runtime")],
+    )?;
+    let response = client.get(url.as_str()).await?;
+    let status = response.status();
+
+    match response.json::<ZlsSelectResponse>().await {
+        Ok(ZlsSelectResponse::Error { message, .. }) => Err(message.into()),
+        Ok(ZlsSelectResponse::Release(entry)) if status.is_success() => {
+            let version = entry.version.ok_or("the release service returned no ZLS version")?;
+            let platform_entry = entry
+                .other_fields
+                .get(platform)
+                .and_then(|v| serde_json::from_value::<PlatformEntry>(v.clone()).ok())
+                .ok_or(format!("ZLS {} has no build for platform {}", version, platform))?;
+            Ok(ZlsBuild {
+                version,
+                file_name: url_file_name(&platform_entry.tarball)?.to_string(),
+                url: platform_entry.tarball,
+                shasum: Some(platform_entry.shasum),
+                signed: true,
+            })
         }
+        _ => Err(format!("the release service answered HTTP {}", status).into()),
     }
-    
-    Err("Failed to find ZLS binary in extracted archive".into())
 }
 
-// Install ZLS for the current Zig version
-pub async fn install_zls(client: &Client, home_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Installing ZLS...");
-    
-    // Get the current Zig version
-    let current_file = get_current_file(home_dir);
-    let current_version = if file_exists(&current_file) {
-        fs::read_to_string(current_file).map(|s| s.trim().to_string())
-            .map_err(|e| format!("Failed to read current version: {}", e))?
-    } else {
-        return Err("No current Zig version set. Please install a Zig version first.".into());
+// Find a tagged GitHub release of the same minor series as a tagged Zig release
+async fn find_github_build(
+    client: &HttpClient,
+    zig_version: &ZigVersion,
+    platform: &str,
+) -> Result<ZlsBuild, Box<dyn std::error::Error>> {
+    let (major, minor, _) = zig_version
+        .triple()
+        .filter(|_| zig_version.is_stable())
+        .ok_or("GitHub releases only match tagged Zig releases")?;
+
+    let releases: Vec<ZlsRelease> = client
+        .get(ZLS_GITHUB_RELEASES_URL)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let (version, release) = releases
+        .into_iter()
+        .filter_map(|r| Some((r.tag_name.trim_start_matches('v').parse::<ZigVersion>().ok()?, r)))
+        .filter(|(v, _)| v.is_stable() && v.triple().is_some_and(|(ma, mi, _)| (ma, mi) == (major, minor)))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .ok_or(format!("no ZLS release is tagged {}.{}.x", major, minor))?;
+
+    // Asset names switched from arch-os to os-arch, so both are accepted
+    let (arch, os) = platform.split_once('-').unwrap_or((platform, ""));
+    let os_arch = format!("{}-{}", os, arch);
+    let asset = release
+        .assets
+        .iter()
+        .filter(|a| a.name.ends_with(".tar.xz") || a.name.ends_with(".zip"))
+        .find(|a| a.name.contains(platform) || a.name.contains(&os_arch))
+        .ok_or(format!("ZLS {} has no build for platform {}", version, platform))?;
+
+    // Releases carry a .minisig per archive, a checksums file, or both
+    let signature_name = format!("{}.minisig", asset.name);
+    let signed = release.assets.iter().any(|a| a.name == signature_name);
+    let checksums = release.assets.iter().find(|a| {
+        let name = a.name.to_ascii_lowercase();
+        name.contains("sha256") || name.contains("checksums")
+    });
+    let shasum = match checksums {
+        Some(checksums) => {
+            let contents = client
+                .get(&checksums.browser_download_url)
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            Some(find_checksum(&contents, &asset.name).ok_or(format!(
+                "{} does not list {}",
+                checksums.name, asset.name
+            ))?)
+        }
+        None => None,
     };
-    
-    println!("Installing ZLS for Zig version {}", current_version);
-    
+
+    Ok(ZlsBuild {
+        version: version.to_string(),
+        url: asset.browser_download_url.clone(),
+        file_name: asset.name.clone(),
+        shasum,
+        signed,
+    })
+}
+
+// Look up a file in a `sha256sum`-style checksums file: `<hash>  <name>`, or
+// `<hash> *<name>` for binary mode. Lines without a full SHA-256 are ignored.
+fn find_checksum(contents: &str, file_name: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let (shasum, name) = line.trim_end().split_once(char::is_whitespace)?;
+        let name = name.trim_start().strip_prefix('*').unwrap_or(name.trim_start());
+        let valid = shasum.len() == 64 && shasum.chars().all(|c| c.is_ascii_hexdigit());
+        (valid && name == file_name).then(|| shasum.to_ascii_lowercase())
+    })
+}
+
+// Pick the ZLS build for a Zig version: the release service first, then
+// tagged GitHub releases
+async fn find_zls_build(
+    client: &HttpClient,
+    base_url: &str,
+    zig_version: &ZigVersion,
+    platform: &str,
+) -> Result<ZlsBuild, Box<dyn std::error::Error>> {
+    let service_error = match select_zls_build(client, base_url, zig_version, platform).await {
+        Ok(build) => return Ok(build),
+        Err(e) => e,
+    };
+    println!("Warning: release service found no ZLS build ({}); trying GitHub releases", service_error);
+
+    find_github_build(client, zig_version, platform).await.map_err(|github_error| {
+        format!(
+            "No ZLS build is compatible with Zig {} on {}: {}; {}. Build one from source with `zpm zls build --zig {} <source>`.",
+            zig_version, platform, service_error, github_error, zig_version
+        )
+        .into()
+    })
+}
+
+// Install ZLS for an installed Zig version, or the active one
+pub async fn install_zls(
+    client: &HttpClient,
+    home_dir: &str,
+    requirement: Option<&VersionReq>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Installing ZLS...");
+    let config = load_config(home_dir)?;
+
+    let (current_version, source) = resolve_zig_version(home_dir, requirement)?;
+
+    println!("Installing ZLS for Zig version {} ({})", current_version, source);
+
     // Get the platform string
     let platform = get_platform_string()?;
-    let zls_platform = match platform.as_str() {
-        "x86_64-macos" => "x86_64-macos",
-        "aarch64-macos" => "aarch64-macos",
-        "x86_64-linux" => "x86_64-linux",
-        "aarch64-linux" => "aarch64-linux",
-        _ => return Err(format!("Platform {} not supported for ZLS", platform).into()),
-    };
-    
-    // Fetch the latest ZLS release
-    let response = client.get(ZLS_RELEASES_URL)
-        .header("User-Agent", "zpm")
-        .send()
-        .await?;
-    
-    let release: ZlsRelease = response.json().await?;
-    
-    // Find the appropriate asset for the platform
-    let zls_asset = release.assets.into_iter()
-        .find(|asset| asset.name.contains(zls_platform))
-        .ok_or(format!("No ZLS asset found for platform {}", zls_platform))?;
-    
-    println!("Downloading ZLS from: {}", zls_asset.browser_download_url);
-    
+
+    // Pick the ZLS build made for this Zig version
+    let build = find_zls_build(client, &config.zls_releases_url, &current_version, &platform).await?;
+    println!("Selected ZLS {} for Zig {}", build.version, current_version);
+
     // Download directory structure
-    let zls_dir = get_version_dir(home_dir, &current_version).join("zls");
-    let cache_dir = get_cache_dir(home_dir);
-    let zls_archive = cache_dir.join(zls_asset.name.clone());
-    
-    // Download the ZLS archive
-    download_file(client, &zls_asset.browser_download_url, &zls_archive).await?;
-    
-    // Create ZLS directory
+    let zls_dir = get_zls_dir(home_dir, &current_version);
+
+    // ZLS runs inside every editor, so an archive that cannot be verified is refused
+    if build.shasum.is_none() && !build.signed {
+        return Err(format!(
+            "ZLS {} has no published checksum or signature; refusing to install it",
+            build.version
+        )
+        .into());
+    }
+    let public_key = build.signed.then_some(config.zls_public_key.as_str());
+
+    // Reuse the cached archive for this build, or download it into the cache
+    let cached = match &build.shasum {
+        Some(shasum) => find_by_sha256(home_dir, shasum),
+        None => find_by_url(home_dir, &build.url),
+    }
+    .filter(|archive| public_key.is_none() || file_exists(&get_signature_path(&archive.path)))
+    .filter(verify_archive);
+    let archive = match cached {
+        Some(archive) => {
+            println!("Using cached archive {} ({})", archive.entry.file_name, archive.entry.sha256);
+            if let Some(public_key) = public_key {
+                let signature = std::fs::read_to_string(get_signature_path(&archive.path))?;
+                verify_file_signature(&archive.path, &signature, public_key, &build.file_name, &mut ()).await?;
+            }
+            touch_archive(&archive)?;
+            archive
+        }
+        None => {
+            println!("Downloading ZLS from: {}", build.url);
+            let download_path = get_download_path(home_dir, &build.file_name);
+            if let Some(parent) = download_path.parent() {
+                create_dir_all(parent).await?;
+            }
+            download_verified(client, &build.url, "", &download_path, public_key, &mut ()).await?;
+            let sha256 = sha256_file(&download_path)?;
+            if let Some(shasum) = &build.shasum
+                && &sha256 != shasum
+            {
+                let _ = std::fs::remove_file(&download_path);
+                let _ = std::fs::remove_file(get_signature_path(&download_path));
+                return Err(format!("Checksum mismatch: expected {}, got {}", shasum, sha256).into());
+            }
+            store_archive(home_dir, &download_path, &sha256, &build.url)?
+        }
+    };
+    if public_key.is_some() {
+        println!("Signature verified ✓");
+    }
+    if build.shasum.is_some() {
+        println!("Checksum verified ✓");
+    }
+
+    // Replace any ZLS installed before
+    if file_exists(&zls_dir) {
+        remove_dir_all(&zls_dir).await?;
+    }
     create_dir_all(&zls_dir).await?;
-    
+
     // Extract the ZLS archive (handling both zip and tar.xz formats)
     println!("Extracting ZLS to {}...", zls_dir.display());
-    
-    if zls_asset.name.ends_with(".zip") {
-        // Use unzip command
-        extract_zip(&zls_archive, &zls_dir)?;
-    } else if zls_asset.name.ends_with(".tar.xz") {
-        // Use tar command
-        extract_tarball(&zls_archive, &zls_dir)?;
+
+    // The binary is located by name afterwards, so the layout is kept as-is
+    if build.file_name.ends_with(".zip") {
+        extract_zip(&archive.path, &zls_dir, 0)?;
+    } else if build.file_name.ends_with(".tar.xz") {
+        extract_tarball(&archive.path, &zls_dir, 0)?;
     } else {
-        return Err(format!("Unknown ZLS archive format: {}", zls_asset.name).into());
+        return Err(format!("Unknown ZLS archive format: {}", build.file_name).into());
     }
-    
-    // Find the ZLS binary
-    let zls_binary = find_zls_binary(&zls_dir)?;
-    
-    // Create a symlink for ZLS in the same directory as Zig
-    let zls_symlink = get_local_bin_dir(home_dir).join("zls");
-    
-    if file_exists(&zls_symlink) {
-        fs::remove_file(&zls_symlink)?;
+
+    // Make sure the archive contained a ZLS binary
+    find_zls_binary(&zls_dir)?;
+
+    // Record which ZLS went with this version
+    let mut record = read_install_record(home_dir, &current_version).unwrap_or_default();
+    record.zls_version = Some(build.version.clone());
+    record.zls_archive_sha256 = Some(archive.entry.sha256.clone());
+    write_install_record(home_dir, &current_version, &record)?;
+
+    // The zls symlink follows the default version, like the zig one
+    if is_default_version(home_dir, &current_version) {
+        link_zls(home_dir, &current_version)?;
+    }
+
+    if let Some(max_size) = &config.cache_max_size {
+        enforce_size_cap(home_dir, max_size);
     }
-    
-    symlink(zls_binary, zls_symlink)?;
-    
-    println!("Successfully installed ZLS for Zig version {}", current_version);
+
+    println!("Successfully installed ZLS {} for Zig version {}", build.version, current_version);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "3f2a9b7c1d0e4f5a6b7c8d9e0f1a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c";
+
+    #[test]
+    fn finds_text_and_binary_mode_lines() {
+        let contents = format!(
+            "{}  zls-x86_64-linux.tar.xz\n{} *zls-aarch64-macos.tar.xz\n",
+            HASH,
+            HASH.to_ascii_uppercase()
+        );
+        assert_eq!(find_checksum(&contents, "zls-x86_64-linux.tar.xz").as_deref(), Some(HASH));
+        assert_eq!(find_checksum(&contents, "zls-aarch64-macos.tar.xz").as_deref(), Some(HASH));
+    }
+
+    #[test]
+    fn ignores_other_files() {
+        let contents = format!("{}  zls-x86_64-linux.tar.xz\n", HASH);
+        assert_eq!(find_checksum(&contents, "zls-x86_64-linux.zip"), None);
+        assert_eq!(find_checksum(&contents, "zls-x86_64-linux.tar"), None);
+        assert_eq!(find_checksum(&contents, ""), None);
+    }
+
+    #[test]
+    fn ignores_malformed_hashes() {
+        for line in [
+            format!("{}  zls.tar.xz", &HASH[..63]),
+            format!("{}0  zls.tar.xz", HASH),
+            format!("{}  zls.tar.xz", HASH.replace('3', "g")),
+            "deadbeef  zls.tar.xz".to_string(),
+            "zls.tar.xz".to_string(),
+            String::new(),
+        ] {
+            assert_eq!(find_checksum(&line, "zls.tar.xz"), None, "{:?} should not match", line);
+        }
+    }
+}
@@ -1,63 +1,72 @@
-use crate::models::ReleaseIndex;
-use crate::utils::{file_exists, get_current_file, get_versions_dir, version_compare};
-use reqwest::Client;
+use crate::http::HttpClient;
+use crate::index::fetch_index;
+use crate::project::{resolve_active_version, ActiveVersion};
+use crate::utils::{get_installed_versions, read_master_alias};
+use crate::version::ZigVersion;
 use std::collections::HashSet;
-use std::fs;
 
-const INDEX_URL: &str = "https://ziglang.org/download/index.json";
-
-// Get installed versions and current version
-async fn get_installed_versions(home_dir: &str) -> Result<(Vec<String>, Option<String>), Box<dyn std::error::Error>> {
-    let versions_dir = get_versions_dir(home_dir);
-    let current_file = get_current_file(home_dir);
-    
-    let current_version = if file_exists(&current_file) {
-        fs::read_to_string(current_file).ok().map(|s| s.trim().to_string())
-    } else {
-        None
-    };
+// Get installed versions and the active version
+async fn get_installed_and_active_versions(home_dir: &str) -> Result<(Vec<ZigVersion>, Option<ActiveVersion>), Box<dyn std::error::Error>> {
+    let active_version = resolve_active_version(home_dir)?;
+    let versions = get_installed_versions(home_dir);
     
-    let mut versions: Vec<String> = Vec::new();
-    
-    if let Ok(dir) = fs::read_dir(versions_dir) {
-        for entry in dir.flatten() {
-            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                versions.push(entry.file_name().to_string_lossy().to_string());
-            }
-        }
-    }
-    
-    Ok((versions, current_version))
-}
-
-// Fetch the list of available releases
-async fn fetch_releases(client: &Client) -> Result<ReleaseIndex, Box<dyn std::error::Error>> {
-    let response = client.get(INDEX_URL).send().await?;
-    let releases: ReleaseIndex = response.json().await?;
-    Ok(releases)
+    Ok((versions, active_version))
 }
 
 // List all available Zig versions with tags
-pub async fn list_versions(client: &Client, home_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn list_versions(client: &HttpClient, home_dir: &str, offline: bool) -> Result<(), Box<dyn std::error::Error>> {
+    // Without an index, still list what is installed
+    let releases = match fetch_index(client, home_dir, offline).await {
+        Ok(releases) => Some(releases),
+        Err(e) => {
+            println!("Warning: {}; showing installed versions only", e);
+            println!();
+            None
+        }
+    };
+    
     println!("Listing all available Zig versions:");
-    println!("  * = current version, I = installed");
+    println!("  * = active version, I = installed");
     println!();
     
-    // Get available versions
-    let releases = fetch_releases(client).await?;
-    
-    // Get installed versions and current version
-    let (installed, current_version) = get_installed_versions(home_dir).await?;
+    // Get installed versions and the active version
+    let (installed, active_version) = get_installed_and_active_versions(home_dir).await?;
     let installed_set: HashSet<_> = installed.into_iter().collect();
+    let current_version = active_version.as_ref().map(|a| a.version.clone());
+    
+    // Nightlies are installed under their concrete dev version
+    let master_version = releases.as_ref().and_then(|r| r.master_version());
+    let master_alias = read_master_alias(home_dir);
     
-    // Prepare and sort all versions
-    let mut all_versions: Vec<String> = releases.versions.keys().cloned().collect();
-    all_versions.sort_by(|a, b| version_compare(a, b));
+    // Prepare and sort all versions (newest first), including installed nightlies no longer in the index
+    let mut all_versions: Vec<ZigVersion> = Vec::new();
+    if let Some(releases) = &releases {
+        all_versions.extend(releases.releases().map(|(v, _)| v));
+        if releases.versions.contains_key("master") {
+            all_versions.push(ZigVersion::Master);
+        }
+    }
+    let listed: HashSet<ZigVersion> = all_versions.iter().cloned().collect();
+    all_versions.extend(
+        installed_set
+            .iter()
+            .filter(|v| !listed.contains(*v) && Some(*v) != master_version.as_ref())
+            .cloned(),
+    );
+    all_versions.sort_by(|a, b| b.cmp(a));
     
     // Display versions with tags
     for version in all_versions {
-        let is_installed = installed_set.contains(&version);
-        let is_current = current_version.as_ref().map(|v| v == &version).unwrap_or(false);
+        let concrete = if version.is_master() {
+            master_version.clone().unwrap_or(ZigVersion::Master)
+        } else {
+            version.clone()
+        };
+        let is_installed = installed_set.contains(&concrete);
+        let is_current = current_version
+            .as_ref()
+            .map(|v| v == &concrete || (v.is_master() && master_alias.as_ref() == Some(&concrete)))
+            .unwrap_or(false);
         
         let mut markers = String::new();
         if is_current {
@@ -72,7 +81,18 @@ pub async fn list_versions(client: &Client, home_dir: &str) -> Result<(), Box<dy
             markers.push(' ');
         }
         
-        println!("  {} {}", markers, version);
+        if version.is_master() {
+            println!("  {} master ({})", markers, concrete);
+        } else if master_alias.as_ref() == Some(&version) {
+            println!("  {} {} (master)", markers, version);
+        } else {
+            println!("  {} {}", markers, version);
+        }
+    }
+    
+    if let Some(active) = active_version {
+        println!();
+        println!("Active version: {} ({})", active.version, active.source);
     }
     
     Ok(())
@@ -1,13 +1,27 @@
 // Command modules
+pub mod bisect;
+pub mod cache;
+pub mod editor_config;
+pub mod exec;
 pub mod install;
 pub mod install_zls;
 pub mod list;
+pub mod shims;
 pub mod uninstall;
 pub mod use_cmd;
+pub mod zls;
+pub mod zls_build;
 
 // Re-export command functions
-pub use install::install;
+pub use bisect::{bisect, prune_bisect_installs};
+pub use cache::{cache_clean, cache_ls, cache_size, cache_verify};
+pub use editor_config::{editor_config, Editor, EditorConfigOptions};
+pub use exec::exec;
+pub use install::{install, InstallOptions};
 pub use install_zls::install_zls;
 pub use list::list_versions;
+pub use shims::shims;
 pub use uninstall::uninstall;
-pub use use_cmd::set_default;
+pub use use_cmd::{link_zls, set_default, use_version};
+pub use zls::{zls_ls, zls_uninstall, zls_which};
+pub use zls_build::zls_build;
@@ -0,0 +1,21 @@
+use crate::commands::use_cmd::set_default;
+use crate::project::get_default_version;
+use crate::shim::{install_shims, remove_shims};
+
+// Install or remove the zig/zls shims
+pub async fn shims(home_dir: &str, remove: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if !remove {
+        install_shims(home_dir)?;
+        println!("zig and zls now follow the project's version on every invocation");
+        return Ok(());
+    }
+
+    remove_shims(home_dir)?;
+
+    // Restore the plain symlink to the global default
+    if let Some(version) = get_default_version(home_dir) {
+        set_default(home_dir, &version).await?;
+    }
+
+    Ok(())
+}
@@ -1,10 +1,20 @@
-use crate::utils::{file_exists, get_current_file, get_version_dir, get_zig_binary, get_zig_symlink};
+use crate::project::get_default_version;
+use crate::utils::{file_exists, get_current_file, get_version_dir, get_zig_binary, get_installed_versions, get_versions_dir, get_zig_symlink, get_zls_symlink, read_master_alias, remove_symlink_into, remove_master_alias, set_master_alias};
+use crate::version_req::VersionReq;
 use std::fs;
 
-// Uninstall a Zig version
-pub async fn uninstall(home_dir: &str, version: &str) -> Result<(), Box<dyn std::error::Error>> {
+// Uninstall the newest installed Zig version matching a requirement
+pub async fn uninstall(home_dir: &str, requirement: &VersionReq) -> Result<(), Box<dyn std::error::Error>> {
+    // `master` is an alias; this resolves to the nightly it points to
+    let version = &requirement
+        .resolve_installed(home_dir)
+        .ok_or(format!("No installed version matches {}", requirement))?;
+    println!("Resolved {} to {}", requirement, version);
     println!("Uninstalling Zig version: {}", version);
     
+    let master_target = read_master_alias(home_dir);
+    let is_master_target = master_target.as_ref() == Some(version);
+    
     let version_dir = get_version_dir(home_dir, version);
     let zig_binary = get_zig_binary(home_dir, version);
     
@@ -16,27 +26,37 @@ pub async fn uninstall(home_dir: &str, version: &str) -> Result<(), Box<dyn std:
     let current_file_path = get_current_file(home_dir);
     let zig_symlink = get_zig_symlink(home_dir);
     
-    let is_current = if file_exists(&current_file_path) {
-        let current = fs::read_to_string(&current_file_path).ok();
-        current.map(|s| s.trim() == version).unwrap_or(false)
-    } else {
-        false
-    };
+    let is_current = get_default_version(home_dir)
+        .map(|v| &v == version || (v.is_master() && is_master_target))
+        .unwrap_or(false);
     
     // Remove the version directory
     fs::remove_dir_all(version_dir)?;
     
+    // Re-point the master alias at the newest remaining nightly, or drop it
+    if is_master_target {
+        match get_installed_versions(home_dir).into_iter().filter(|v| v.is_dev()).max() {
+            Some(newest) => set_master_alias(home_dir, &newest)?,
+            None => remove_master_alias(home_dir)?,
+        }
+    }
+    
     // If this was the current version, remove the symlink and current file
     if is_current {
-        if file_exists(&zig_symlink) {
-            fs::remove_file(zig_symlink)?;
-        }
+        // The version is gone, so the symlink dangles and only its target is checked
+        remove_symlink_into(&zig_symlink, &get_versions_dir(home_dir))?;
         if file_exists(&current_file_path) {
             fs::remove_file(current_file_path)?;
         }
         println!("Removed default version {}", version);
     }
     
+    // Don't leave the zls symlink pointing at the removed version
+    let zls_symlink = get_zls_symlink(home_dir);
+    if !file_exists(&zls_symlink) {
+        remove_symlink_into(&zls_symlink, &get_versions_dir(home_dir))?;
+    }
+    
     println!("Successfully uninstalled Zig version {}", version);
     Ok(())
 }
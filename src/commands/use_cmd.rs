@@ -1,11 +1,14 @@
-use crate::utils::{file_exists, get_current_file, get_local_bin_dir, get_zig_binary, get_zig_symlink};
-use std::fs;
-use std::io::Write;
-use std::os::unix::fs::symlink;
+use crate::shim::is_shim;
+use crate::utils::{
+    file_exists, find_zls_binary, get_current_file, get_local_bin_dir, get_versions_dir, get_zig_binary,
+    get_zig_symlink, get_zls_dir, get_zls_symlink, remove_symlink_into, replace_symlink, write_atomic,
+};
+use crate::version::ZigVersion;
+use crate::version_req::VersionReq;
 use tokio::fs::create_dir_all;
 
 // Set the default Zig version
-pub async fn set_default(home_dir: &str, version: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn set_default(home_dir: &str, version: &ZigVersion) -> Result<(), Box<dyn std::error::Error>> {
     println!("Setting default version to: {}", version);
     
     let zig_binary = get_zig_binary(home_dir, version);
@@ -20,18 +23,41 @@ pub async fn set_default(home_dir: &str, version: &str) -> Result<(), Box<dyn st
     let local_bin_dir = get_local_bin_dir(home_dir);
     create_dir_all(local_bin_dir).await?;
     
-    // Remove existing symlink if it exists
-    if file_exists(&zig_symlink) {
-        fs::remove_file(&zig_symlink)?;
+    // The shim resolves the version itself, so only the plain symlink needs updating
+    if !is_shim(&zig_symlink) {
+        replace_symlink(&zig_binary, &zig_symlink)?;
     }
-    
-    // Create new symlink
-    symlink(&zig_binary, zig_symlink)?;
+    link_zls(home_dir, version)?;
     
     // Update current file
-    let mut file = std::fs::File::create(current_file)?;
-    file.write_all(version.as_bytes())?;
+    write_atomic(&current_file, version.to_string())?;
     
     println!("Default version set to: {}", version);
     Ok(())
 }
+
+// Point the zls symlink at a version's ZLS, or remove it when that version has none
+pub fn link_zls(home_dir: &str, version: &ZigVersion) -> Result<(), Box<dyn std::error::Error>> {
+    let zls_symlink = get_zls_symlink(home_dir);
+    if is_shim(&zls_symlink) {
+        return Ok(());
+    }
+    
+    match find_zls_binary(&get_zls_dir(home_dir, version)) {
+        Ok(zls_binary) => replace_symlink(zls_binary, &zls_symlink)?,
+        // A zls the user put there themselves is left alone
+        Err(_) => remove_symlink_into(&zls_symlink, &get_versions_dir(home_dir))?,
+    }
+    Ok(())
+}
+
+// Set the newest installed version matching a requirement as the default
+pub async fn use_version(home_dir: &str, requirement: &VersionReq) -> Result<ZigVersion, Box<dyn std::error::Error>> {
+    let version = requirement
+        .resolve_installed(home_dir)
+        .ok_or(format!("No installed version matches {}", requirement))?;
+    println!("Resolved {} to {}", requirement, version);
+    
+    set_default(home_dir, &version).await?;
+    Ok(version)
+}
@@ -0,0 +1,106 @@
+use crate::project::{is_default_version, resolve_active_version};
+use crate::record::{read_install_record, write_install_record};
+use crate::utils::{
+    file_exists, find_zls_binary, get_installed_versions, get_versions_dir, get_zls_dir, get_zls_symlink,
+    read_master_alias, remove_symlink_into,
+};
+use crate::version::ZigVersion;
+use crate::version_req::VersionReq;
+use std::cmp::Reverse;
+use tokio::fs::remove_dir_all;
+
+// The Zig version a ZLS command is for, and why: the newest installed version
+// matching a requirement, or the active version. `master` is resolved to the
+// nightly it points at, since ZLS builds are chosen for a concrete version.
+pub fn resolve_zig_version(
+    home_dir: &str,
+    requirement: Option<&VersionReq>,
+) -> Result<(ZigVersion, String), Box<dyn std::error::Error>> {
+    let (version, source) = match requirement {
+        Some(requirement) => {
+            let version = requirement
+                .resolve_installed(home_dir)
+                .ok_or(format!("No installed Zig version matches {}", requirement))?;
+            (version, format!("resolved from {}", requirement))
+        }
+        None => {
+            let active = resolve_active_version(home_dir)?
+                .ok_or("No current Zig version set. Please install a Zig version first.")?;
+            (active.version, active.source.to_string())
+        }
+    };
+
+    if version.is_master() {
+        let nightly = read_master_alias(home_dir)
+            .ok_or("Version master is not installed. Run `zpm install master` first.")?;
+        return Ok((nightly, format!("master, {}", source)));
+    }
+    Ok((version, source))
+}
+
+// List installed Zig versions with the ZLS installed for each
+pub async fn zls_ls(home_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut versions = get_installed_versions(home_dir);
+    if versions.is_empty() {
+        println!("No Zig versions installed");
+        return Ok(());
+    }
+    versions.sort_by_key(|v| Reverse(v.clone()));
+
+    println!("  * = default version");
+    println!();
+    println!("    {:<32} ZLS", "ZIG");
+    for version in &versions {
+        let marker = if is_default_version(home_dir, version) { '*' } else { ' ' };
+        let zls = match find_zls_binary(&get_zls_dir(home_dir, version)) {
+            // Installs from before install records don't know their ZLS release
+            Ok(_) => read_install_record(home_dir, version)
+                .and_then(|record| record.zls_version)
+                .unwrap_or_else(|| "unknown".to_string()),
+            Err(_) => "-".to_string(),
+        };
+        println!("  {} {:<32} {}", marker, version.to_string(), zls);
+    }
+
+    Ok(())
+}
+
+// Remove the ZLS installed for a Zig version
+pub async fn zls_uninstall(home_dir: &str, requirement: Option<&VersionReq>) -> Result<(), Box<dyn std::error::Error>> {
+    let (version, source) = resolve_zig_version(home_dir, requirement)?;
+    println!("Uninstalling ZLS for Zig version {} ({})", version, source);
+
+    let zls_dir = get_zls_dir(home_dir, &version);
+    if !file_exists(&zls_dir) {
+        return Err(format!("ZLS is not installed for Zig version {}", version).into());
+    }
+    remove_dir_all(&zls_dir).await?;
+
+    if let Some(mut record) = read_install_record(home_dir, &version) {
+        record.zls_version = None;
+        record.zls_archive_sha256 = None;
+        write_install_record(home_dir, &version, &record)?;
+    }
+
+    // Don't leave the zls symlink pointing at the removed build
+    let zls_symlink = get_zls_symlink(home_dir);
+    if !file_exists(&zls_symlink) {
+        remove_symlink_into(&zls_symlink, &get_versions_dir(home_dir))?;
+    }
+
+    println!("Successfully uninstalled ZLS for Zig version {}", version);
+    Ok(())
+}
+
+// Print the path of the ZLS binary for a Zig version
+pub async fn zls_which(home_dir: &str, requirement: Option<&VersionReq>) -> Result<(), Box<dyn std::error::Error>> {
+    let (version, _) = resolve_zig_version(home_dir, requirement)?;
+    let zls_binary = find_zls_binary(&get_zls_dir(home_dir, &version)).map_err(|_| {
+        format!(
+            "ZLS is not installed for Zig version {}. Run `zpm zls install {}`.",
+            version, version
+        )
+    })?;
+    println!("{}", zls_binary.display());
+    Ok(())
+}
@@ -0,0 +1,288 @@
+use crate::cache::{
+    enforce_size_cap, find_by_sha256, find_by_url, get_download_path, list_archives, store_archive, touch_archive,
+    verify_archive, CachedArchive,
+};
+use crate::commands::use_cmd::link_zls;
+use crate::commands::zls::resolve_zig_version;
+use crate::config::load_config;
+use crate::download::{download_file, sha256_file, url_file_name};
+use crate::extract::{extract_tarball, extract_zip};
+use crate::http::HttpClient;
+use crate::project::is_default_version;
+use crate::record::{read_install_record, write_install_record};
+use crate::utils::{
+    file_exists, find_zls_binary, get_version_dir, get_zig_binary, get_zls_build_dir, get_zls_dir,
+};
+use crate::version_req::VersionReq;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Kept in the version directory so it outlives a failed build
+const BUILD_LOG: &str = "zls-build.log";
+// How much of a failed build's log is repeated on the terminal
+const LOG_TAIL_LINES: usize = 20;
+
+// A ZLS source tree to build
+enum ZlsSource {
+    // A checkout, built in place
+    Checkout(PathBuf),
+    // A source archive, unpacked into the build directory first
+    Archive {
+        path: PathBuf,
+        file_name: String,
+        // Set when the archive is in the cache
+        sha256: Option<String>,
+    },
+}
+
+fn check_sha256(expected: Option<&str>, actual: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match expected {
+        Some(expected) if !expected.eq_ignore_ascii_case(actual) => {
+            Err(format!("Checksum mismatch: expected {}, got {}", expected, actual).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Use the cached archive downloaded from a URL, or download it into the cache
+async fn fetch_source_archive(
+    client: &HttpClient,
+    home_dir: &str,
+    url: &str,
+    sha256: Option<&str>,
+    offline: bool,
+) -> Result<CachedArchive, Box<dyn std::error::Error>> {
+    let cached = match sha256 {
+        Some(sha256) => find_by_sha256(home_dir, &sha256.to_ascii_lowercase()),
+        None => find_by_url(home_dir, url),
+    }
+    .filter(verify_archive);
+    if let Some(archive) = cached {
+        println!("Using cached archive {} ({})", archive.entry.file_name, archive.entry.sha256);
+        touch_archive(&archive)?;
+        return Ok(archive);
+    }
+    if offline {
+        return Err(format!("{} is not in the cache and --offline is set", url).into());
+    }
+    if sha256.is_none() {
+        println!("Warning: no --sha256 given; the source archive is not verified");
+    }
+
+    println!("Downloading ZLS source from: {}", url);
+    let download_path = get_download_path(home_dir, url_file_name(url)?);
+    if let Some(parent) = download_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    download_file(client, url, &download_path, &mut ()).await?;
+    let actual = sha256_file(&download_path)?;
+    if let Err(e) = check_sha256(sha256, &actual) {
+        let _ = std::fs::remove_file(&download_path);
+        return Err(e);
+    }
+    store_archive(home_dir, &download_path, &actual, url)
+}
+
+// Work out what a `zpm zls build` source argument refers to: a URL, a checkout,
+// an archive file, or a cached archive named by file name or SHA-256 prefix
+async fn resolve_source(
+    client: &HttpClient,
+    home_dir: &str,
+    source: &str,
+    sha256: Option<&str>,
+    offline: bool,
+) -> Result<ZlsSource, Box<dyn std::error::Error>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let archive = fetch_source_archive(client, home_dir, source, sha256, offline).await?;
+        return Ok(ZlsSource::Archive {
+            path: archive.path,
+            file_name: archive.entry.file_name,
+            sha256: Some(archive.entry.sha256),
+        });
+    }
+
+    let path = Path::new(source);
+    if path.is_dir() {
+        return Ok(ZlsSource::Checkout(path.canonicalize()?));
+    }
+    if path.is_file() {
+        check_sha256(sha256, &sha256_file(path)?)?;
+        return Ok(ZlsSource::Archive {
+            path: path.to_path_buf(),
+            file_name: path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string(),
+            sha256: None,
+        });
+    }
+
+    let archive = list_archives(home_dir)
+        .into_iter()
+        .find(|a| a.entry.file_name == source || (source.len() >= 8 && a.entry.sha256.starts_with(source)))
+        .filter(verify_archive)
+        .ok_or(format!(
+            "{} is not a directory, an archive, a URL or a cached archive",
+            source
+        ))?;
+    check_sha256(sha256, &archive.entry.sha256)?;
+    println!("Using cached archive {} ({})", archive.entry.file_name, archive.entry.sha256);
+    touch_archive(&archive)?;
+    Ok(ZlsSource::Archive {
+        path: archive.path,
+        file_name: archive.entry.file_name,
+        sha256: Some(archive.entry.sha256),
+    })
+}
+
+// Find the directory with build.zig in an unpacked source archive, which
+// usually has a single top-level directory
+fn find_source_root(dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if dir.join("build.zig").is_file() {
+        return Ok(dir.to_path_buf());
+    }
+    std::fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| path.join("build.zig").is_file())
+        .ok_or("No build.zig found in the ZLS source".into())
+}
+
+fn print_log_tail(log_path: &Path) {
+    let Ok(log) = std::fs::read_to_string(log_path) else {
+        return;
+    };
+    let lines: Vec<&str> = log.lines().collect();
+    for line in &lines[lines.len().saturating_sub(LOG_TAIL_LINES)..] {
+        println!("  {}", line);
+    }
+}
+
+// Unpack the source if needed, build it into the build directory and move the
+// result into the version's zls directory. Returns the installed binary.
+fn build_zls(
+    zig_binary: &Path,
+    source: &ZlsSource,
+    build_dir: &Path,
+    zls_dir: &Path,
+    log_path: &Path,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let source_dir = match source {
+        ZlsSource::Checkout(dir) => {
+            if !dir.join("build.zig").is_file() {
+                return Err(format!("{} has no build.zig", dir.display()).into());
+            }
+            dir.clone()
+        }
+        ZlsSource::Archive { path, file_name, .. } => {
+            let unpack_dir = build_dir.join("src");
+            std::fs::create_dir_all(&unpack_dir)?;
+            println!("Extracting {}...", file_name);
+            if file_name.ends_with(".tar.xz") {
+                extract_tarball(path, &unpack_dir, 0)?;
+            } else if file_name.ends_with(".zip") {
+                extract_zip(path, &unpack_dir, 0)?;
+            } else {
+                return Err(format!("Unsupported source archive {} (expected .tar.xz or .zip)", file_name).into());
+            }
+            find_source_root(&unpack_dir)?
+        }
+    };
+
+    // Installing with --prefix keeps the build output out of the source tree
+    let prefix = build_dir.join("out");
+    let args = ["build", "-Doptimize=ReleaseSafe", "--prefix"];
+    let mut log = File::create(log_path)?;
+    writeln!(log, "# in {}", source_dir.display())?;
+    writeln!(log, "$ {} {} {}", zig_binary.display(), args.join(" "), prefix.display())?;
+
+    println!("Building ZLS in {} (log: {})", source_dir.display(), log_path.display());
+    let status = Command::new(zig_binary)
+        .args(args)
+        .arg(&prefix)
+        .current_dir(&source_dir)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .status()
+        .map_err(|e| format!("Failed to run {}: {}", zig_binary.display(), e))?;
+    if !status.success() {
+        print_log_tail(log_path);
+        return Err(format!("ZLS build failed ({}); see {}", status, log_path.display()).into());
+    }
+    find_zls_binary(&prefix).map_err(|_| "The build finished without producing a zls binary")?;
+
+    // Replace any ZLS installed before
+    if file_exists(zls_dir) {
+        std::fs::remove_dir_all(zls_dir)?;
+    }
+    std::fs::rename(&prefix, zls_dir)?;
+    find_zls_binary(zls_dir)
+}
+
+// Ask a ZLS binary for its version
+fn zls_binary_version(zls_binary: &Path) -> Option<String> {
+    let output = Command::new(zls_binary).arg("--version").output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|version| !version.is_empty())
+}
+
+// Build ZLS from source with an installed Zig version and install it for that version
+pub async fn zls_build(
+    client: &HttpClient,
+    home_dir: &str,
+    source: &str,
+    requirement: Option<&VersionReq>,
+    sha256: Option<&str>,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = load_config(home_dir)?;
+    let (version, version_source) = resolve_zig_version(home_dir, requirement)?;
+    let zig_binary = get_zig_binary(home_dir, &version);
+    if !file_exists(&zig_binary) {
+        return Err(format!("Version {} is not installed", version).into());
+    }
+    println!("Building ZLS for Zig version {} ({})", version, version_source);
+
+    let source = resolve_source(client, home_dir, source, sha256, offline).await?;
+
+    let build_dir = get_zls_build_dir(home_dir, &version);
+    if file_exists(&build_dir) {
+        std::fs::remove_dir_all(&build_dir)?;
+    }
+    std::fs::create_dir_all(&build_dir)?;
+    let log_path = get_version_dir(home_dir, &version).join(BUILD_LOG);
+    let zls_dir = get_zls_dir(home_dir, &version);
+
+    let result = build_zls(&zig_binary, &source, &build_dir, &zls_dir, &log_path);
+    let _ = std::fs::remove_dir_all(&build_dir);
+    let zls_binary = result?;
+
+    // Record the build; a cached source archive stays referenced by it
+    let zls_version = zls_binary_version(&zls_binary);
+    let mut record = read_install_record(home_dir, &version).unwrap_or_default();
+    record.zls_version = zls_version.clone();
+    record.zls_archive_sha256 = match &source {
+        ZlsSource::Archive { sha256, .. } => sha256.clone(),
+        ZlsSource::Checkout(_) => None,
+    };
+    write_install_record(home_dir, &version, &record)?;
+
+    // The zls symlink follows the default version, like the zig one
+    if is_default_version(home_dir, &version) {
+        link_zls(home_dir, &version)?;
+    }
+
+    if let Some(max_size) = &config.cache_max_size {
+        enforce_size_cap(home_dir, max_size);
+    }
+
+    println!(
+        "Successfully built ZLS {} for Zig version {}",
+        zls_version.as_deref().unwrap_or("(unknown version)"),
+        version
+    );
+    Ok(())
+}
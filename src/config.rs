@@ -0,0 +1,62 @@
+use crate::download::{ZLS_PUBLIC_KEY, ZSF_PUBLIC_KEY};
+use crate::utils::get_config_file;
+use serde::Deserialize;
+
+const ZLS_RELEASES_URL: &str = "https://releases.zigtools.org";
+
+// Settings read from ~/.zpm/config.json; every field is optional
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    // Seconds a cached release index is used without revalidating it
+    pub index_ttl: u64,
+    // Attempts per network request, including the first
+    pub retry_attempts: u32,
+    // Backoff before the first retry, doubled on each further retry
+    pub retry_backoff_ms: u64,
+    pub retry_max_backoff_ms: u64,
+    // Timeouts in seconds
+    pub connect_timeout: u64,
+    pub read_timeout: u64,
+    // Mirrors to download from instead of the community list; empty disables mirrors
+    pub mirrors: Option<Vec<String>>,
+    // Minisign key archives must be signed with; override for forks
+    pub public_key: String,
+    // Size the archive cache is trimmed to after each install, e.g. "5G"
+    pub cache_max_size: Option<String>,
+    // Base URL of the zigtools release service used to pick ZLS builds
+    pub zls_releases_url: String,
+    // Minisign key ZLS archives must be signed with
+    pub zls_public_key: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            index_ttl: 3600,
+            retry_attempts: 4,
+            retry_backoff_ms: 500,
+            retry_max_backoff_ms: 30_000,
+            connect_timeout: 10,
+            read_timeout: 30,
+            mirrors: None,
+            public_key: ZSF_PUBLIC_KEY.to_string(),
+            cache_max_size: None,
+            zls_releases_url: ZLS_RELEASES_URL.to_string(),
+            zls_public_key: ZLS_PUBLIC_KEY.to_string(),
+        }
+    }
+}
+
+// Load the config file, falling back to defaults when it does not exist
+pub fn load_config(home_dir: &str) -> Result<Config, Box<dyn std::error::Error>> {
+    let path = get_config_file(home_dir);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e).into()),
+    };
+
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid config file {}: {}", path.display(), e).into())
+}
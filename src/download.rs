@@ -1,66 +1,365 @@
-use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
+use crate::http::{describe_error, is_transient, HttpClient};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use minisign_verify::{PublicKey, Signature, StreamVerifier};
+use reqwest::header::{HeaderMap, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use std::fs::File;
-use std::io::copy;
-use std::path::Path;
-use std::process::Command;
-use tokio::fs::File as AsyncFile;
+use std::future::Future;
+use std::io::{copy, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::fs::{File as AsyncFile, OpenOptions};
 use tokio::io::AsyncWriteExt;
 
-// Download a file from a URL to a destination path
+// Zig Software Foundation minisign public key
+pub const ZSF_PUBLIC_KEY: &str = "RWSGOq2NVecA2UPNdBUZykf1CCb147pkmdtYxgb3Ti+JO/wCYvhbAb/U";
+
+// zigtools minisign public key for ZLS release builds
+pub const ZLS_PUBLIC_KEY: &str = "RWR+9B91GBZ0zOjh6Lr17+zKf5BoSuFvrx2xSeDE57uIYvnKBGmMjOex";
+
+// Sidecar metadata for a partial download, used to decide whether it can be resumed
+#[derive(Serialize, Deserialize, Debug)]
+struct PartialDownload {
+    url: String,
+    total_size: Option<u64>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl PartialDownload {
+    // The validator sent with If-Range, preferring a strong ETag
+    fn validator(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn same_file_name(a: &str, b: &str) -> bool {
+    matches!((url_file_name(a), url_file_name(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+fn read_partial(meta_path: &Path) -> Option<PartialDownload> {
+    std::fs::read_to_string(meta_path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+}
+
+// Removes a part file that could never be resumed when the download stops,
+// whether it fails or is cancelled. Resumable part files are kept.
+struct PartFileGuard {
+    part_path: PathBuf,
+    meta_path: PathBuf,
+}
+
+impl Drop for PartFileGuard {
+    fn drop(&mut self) {
+        if read_partial(&self.meta_path).is_some_and(|p| p.validator().is_some()) {
+            return;
+        }
+        let _ = std::fs::remove_file(&self.part_path);
+        let _ = std::fs::remove_file(&self.meta_path);
+    }
+}
+
+fn header_value(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+// Parse the start offset and total size from a `bytes start-end/total` Content-Range
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.parse().ok()?, total.parse().ok()))
+}
+
+// Returned by `DownloadSink::write`; boxed so sinks can be used as `dyn DownloadSink`
+pub type SinkWrite<'a> = Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>> + 'a>>;
+
+// Receives the bytes of a download, in order, as they arrive
+pub trait DownloadSink {
+    // The download is starting over from the first byte
+    fn restart(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+    // Awaited, so a slow sink holds the download back without blocking the runtime
+    fn write<'a>(&'a mut self, chunk: &'a [u8]) -> SinkWrite<'a>;
+    // Where the download's progress bar goes when the sink shows progress of its own
+    fn progress(&self) -> Option<&MultiProgress> {
+        None
+    }
+    // Whether the sink checks the bytes against a signature, so a partial
+    // download of the same file from another mirror can be continued
+    fn verifies(&self) -> bool {
+        false
+    }
+}
+
+// Print a line without breaking up the sink's progress bars
+pub fn sink_println(sink: &dyn DownloadSink, line: &str) {
+    match sink.progress() {
+        Some(progress) => progress.suspend(|| println!("{}", line)),
+        None => println!("{}", line),
+    }
+}
+
+// Discards the bytes, for plain downloads
+impl DownloadSink for () {
+    fn restart(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn write<'a>(&'a mut self, _chunk: &'a [u8]) -> SinkWrite<'a> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+// Feed bytes `start..end` of a file to a sink
+async fn feed_range(
+    path: &Path,
+    start: u64,
+    end: u64,
+    sink: &mut dyn DownloadSink,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut reader = file.take(end.saturating_sub(start));
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        sink.write(&buf[..n]).await?;
+    }
+}
+
+// Feed a whole file to a sink, e.g. an archive already in the cache
+pub async fn feed_file(path: &Path, sink: &mut dyn DownloadSink) -> Result<(), Box<dyn std::error::Error>> {
+    sink.restart()?;
+    feed_range(path, 0, u64::MAX, sink).await
+}
+
+// A failed download attempt: interrupted transfers are retried, anything else is not
+enum AttemptError {
+    Interrupted(String),
+    Failed(Box<dyn std::error::Error>),
+}
+
+impl<E: Into<Box<dyn std::error::Error>>> From<E> for AttemptError {
+    fn from(e: E) -> Self {
+        AttemptError::Failed(e.into())
+    }
+}
+
+// Download a file from a URL to a destination path. Data is written to a
+// `.part` file next to the destination and resumed with a Range request when
+// a previous attempt left one behind; the file is renamed into place once complete.
+// An interrupted transfer is retried from where it stopped. Every byte of the
+// file, including any resumed from disk, is passed to `sink` as it is written.
 pub async fn download_file(
-    client: &Client,
+    client: &HttpClient,
     url: &str,
     dest_path: &Path,
+    sink: &mut dyn DownloadSink,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let max_attempts = client.policy().max_attempts;
+    let mut attempt = 1;
+    // Bytes already passed to the sink
+    let mut fed = 0;
+    sink.restart()?;
+    loop {
+        match download_attempt(client, url, dest_path, sink, &mut fed).await {
+            Ok(()) => return Ok(()),
+            Err(AttemptError::Failed(e)) => return Err(e),
+            Err(AttemptError::Interrupted(reason)) if attempt >= max_attempts => {
+                return Err(format!(
+                    "Download of {} failed on attempt {}/{}: {}",
+                    url, attempt, max_attempts, reason
+                )
+                .into());
+            }
+            Err(AttemptError::Interrupted(reason)) => {
+                client.backoff(url, attempt, &reason, None).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+async fn download_attempt(
+    client: &HttpClient,
+    url: &str,
+    dest_path: &Path,
+    sink: &mut dyn DownloadSink,
+    fed: &mut u64,
+) -> Result<(), AttemptError> {
     // Get file name for display
     let file_name = dest_path
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("file");
+    let part_path = with_suffix(dest_path, ".part");
+    let meta_path = with_suffix(dest_path, ".part.json");
+    let _guard = PartFileGuard { part_path: part_path.clone(), meta_path: meta_path.clone() };
+
+    // Resume a partial download that has a validator. One made from another URL
+    // for the same file (another mirror) can't be checked with If-Range, so it is
+    // only continued when the sink verifies the whole file.
+    let partial = read_partial(&meta_path).filter(|p| {
+        p.validator().is_some() && (p.url == url || (sink.verifies() && same_file_name(&p.url, url)))
+    });
+    let offset = match &partial {
+        Some(_) => std::fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0),
+        None => 0,
+    };
 
     // Send request
-    let mut response = client.get(url).send().await?;
-    let total_size = response.content_length().unwrap_or(0);
+    let mut headers = HeaderMap::new();
+    if let Some(partial) = &partial
+        && offset > 0
+    {
+        headers.insert(RANGE, format!("bytes={}-", offset).parse()?);
+        if partial.url == url {
+            headers.insert(IF_RANGE, partial.validator().unwrap_or_default().parse()?);
+        }
+    }
+    let response = client.get_with_headers(url, headers).await?;
+
+    // The part file may already hold the whole file
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE
+        && partial.as_ref().and_then(|p| p.total_size) == Some(offset)
+    {
+        feed_range(&part_path, *fed, offset, sink).await?;
+        std::fs::rename(&part_path, dest_path)?;
+        let _ = std::fs::remove_file(&meta_path);
+        println!("Already downloaded {}", file_name);
+        return Ok(());
+    }
+
+    let mut response = response.error_for_status()?;
+
+    // A 206 continues the part file; anything else starts over
+    let (offset, total_size) = match response.status() {
+        StatusCode::PARTIAL_CONTENT => {
+            let (start, total) = header_value(&response, CONTENT_RANGE)
+                .as_deref()
+                .and_then(parse_content_range)
+                .ok_or("Invalid Content-Range in partial response")?;
+            if start != offset {
+                return Err(format!("Server resumed at byte {} instead of {}", start, offset).into());
+            }
+            // Catch the sink up with what is already on disk
+            feed_range(&part_path, *fed, offset, sink).await?;
+            *fed = offset;
+            (offset, total.or(partial.as_ref().and_then(|p| p.total_size)))
+        }
+        _ => {
+            let metadata = PartialDownload {
+                url: url.to_string(),
+                total_size: response.content_length(),
+                etag: header_value(&response, ETAG),
+                last_modified: header_value(&response, LAST_MODIFIED),
+            };
+            std::fs::write(&meta_path, serde_json::to_string(&metadata)?)?;
+            if *fed > 0 {
+                sink.restart()?;
+                *fed = 0;
+            }
+            (0, metadata.total_size)
+        }
+    };
 
     // Create progress bar
-    let pb = ProgressBar::new(total_size);
+    let pb = ProgressBar::new(total_size.unwrap_or(0));
+    let pb = match sink.progress() {
+        Some(progress) => progress.insert(0, pb),
+        None => pb,
+    };
     pb.set_style(
         ProgressStyle::default_bar()
             .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta}) {msg}")
             .unwrap()
             .progress_chars("#>-"),
     );
-    pb.set_message(format!("Downloading {}", file_name));
+    if offset > 0 {
+        pb.set_message(format!("Resuming {}", file_name));
+    } else {
+        pb.set_message(format!("Downloading {}", file_name));
+    }
+    pb.set_position(offset);
+    pb.reset_eta();
 
     // Download with progress
-    let mut file = AsyncFile::create(dest_path).await?;
-    let mut downloaded: u64 = 0;
+    let mut file = if offset > 0 {
+        OpenOptions::new().append(true).open(&part_path).await?
+    } else {
+        AsyncFile::create(&part_path).await?
+    };
+    let mut downloaded: u64 = offset;
 
-    while let Some(chunk) = response.chunk().await? {
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) if is_transient(&e) => {
+                file.flush().await?;
+                pb.abandon();
+                return Err(AttemptError::Interrupted(describe_error(&e)));
+            }
+            Err(e) => return Err(e.into()),
+        };
         file.write_all(&chunk).await?;
+        sink.write(&chunk).await?;
+        *fed += chunk.len() as u64;
         downloaded += chunk.len() as u64;
         pb.set_position(downloaded);
     }
+    file.flush().await?;
 
+    if let Some(total_size) = total_size
+        && downloaded != total_size
+    {
+        pb.abandon();
+        return Err(AttemptError::Interrupted(format!(
+            "got {} of {} bytes",
+            downloaded, total_size
+        )));
+    }
+
+    std::fs::rename(&part_path, dest_path)?;
+    let _ = std::fs::remove_file(&meta_path);
     pb.finish_with_message(format!("Downloaded {}", file_name));
 
     Ok(())
 }
 
-// Verify file checksum using SHA256
-pub fn verify_checksum(
-    file_path: &Path,
-    expected_shasum: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+// Hash a file with SHA-256, as lowercase hex
+pub fn sha256_file(file_path: &Path) -> std::io::Result<String> {
     use sha2::Digest;
 
     let mut file = File::open(file_path)?;
     let mut hasher = sha2::Sha256::new();
     copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
 
-    let actual_hash = hasher.finalize();
-    let actual_shasum = format!("{:x}", actual_hash);
+// Verify file checksum using SHA256
+pub fn verify_checksum(
+    file_path: &Path,
+    expected_shasum: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let actual_shasum = sha256_file(file_path)?;
 
     if actual_shasum != expected_shasum {
         return Err(format!(
@@ -73,44 +372,154 @@ pub fn verify_checksum(
     Ok(())
 }
 
-// Extract a tar.xz archive to a destination directory
-pub fn extract_tarball(
-    archive_path: &Path,
-    dest_dir: &Path,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Use tar command to extract
-    let status = Command::new("tar")
-        .args([
-            "-xJf",
-            archive_path.to_str().unwrap(),
-            "-C",
-            dest_dir.to_str().unwrap(),
-            "--strip-components=1",
-        ])
-        .status()?;
+// Fetch the detached minisign signature published next to an archive
+pub async fn fetch_signature(client: &HttpClient, url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let signature_url = format!("{}.minisig", url);
+    let response = client.get(&signature_url).await?.error_for_status()?;
+    Ok(response.text().await?)
+}
+
+// The file name a signature's trusted comment vouches for, e.g. from
+// `timestamp:1718125925	file:zig-linux-x86_64-0.13.0.tar.xz	hashed`
+fn signed_file_name(signature: &Signature) -> Option<&str> {
+    signature
+        .trusted_comment()
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("file:"))
+}
+
+pub fn decode_public_key(public_key: &str) -> Result<PublicKey, Box<dyn std::error::Error>> {
+    PublicKey::from_base64(public_key).map_err(|e| format!("Invalid minisign public key: {}", e).into())
+}
+
+pub fn decode_signature(signature: &str) -> Result<Signature, Box<dyn std::error::Error>> {
+    Signature::decode(signature).map_err(|e| format!("Invalid minisign signature: {}", e).into())
+}
+
+// Checks a minisign signature over the bytes passing through to another sink
+pub struct SignatureSink<'k, 's> {
+    public_key: &'k PublicKey,
+    signature: &'k Signature,
+    verifier: StreamVerifier<'k>,
+    inner: &'s mut dyn DownloadSink,
+}
 
-    if !status.success() {
-        return Err("Failed to extract tarball".into());
+impl<'k, 's> SignatureSink<'k, 's> {
+    pub fn new(
+        public_key: &'k PublicKey,
+        signature: &'k Signature,
+        inner: &'s mut dyn DownloadSink,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let verifier = public_key
+            .verify_stream(signature)
+            .map_err(|e| format!("Signature verification failed: {}", e))?;
+        Ok(SignatureSink { public_key, signature, verifier, inner })
     }
 
-    Ok(())
+    // Check the signature over everything written, and that it was made for a
+    // file of the expected name
+    pub fn finish(mut self, expected_file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        // This also authenticates the trusted comment
+        self.verifier
+            .finalize()
+            .map_err(|e| format!("Signature verification failed: {}", e))?;
+
+        match signed_file_name(self.signature) {
+            Some(name) if name == expected_file_name => Ok(()),
+            Some(name) => Err(format!(
+                "Signature is for {}, not {}; refusing to use the archive",
+                name, expected_file_name
+            )
+            .into()),
+            None => Err(format!(
+                "Signature for {} does not name the signed file; refusing to use the archive",
+                expected_file_name
+            )
+            .into()),
+        }
+    }
 }
 
-// Extract a zip archive to a destination directory
-pub fn extract_zip(archive_path: &Path, dest_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    // Use unzip command to extract
-    let status = Command::new("unzip")
-        .args([
-            "-o",
-            archive_path.to_str().unwrap(),
-            "-d",
-            dest_dir.to_str().unwrap(),
-        ])
-        .status()?;
+impl DownloadSink for SignatureSink<'_, '_> {
+    fn restart(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.verifier = self
+            .public_key
+            .verify_stream(self.signature)
+            .map_err(|e| format!("Signature verification failed: {}", e))?;
+        self.inner.restart()
+    }
 
-    if !status.success() {
-        return Err("Failed to extract zip archive".into());
+    fn write<'a>(&'a mut self, chunk: &'a [u8]) -> SinkWrite<'a> {
+        self.verifier.update(chunk);
+        self.inner.write(chunk)
     }
 
+    fn progress(&self) -> Option<&MultiProgress> {
+        self.inner.progress()
+    }
+
+    fn verifies(&self) -> bool {
+        true
+    }
+}
+
+// Verify a file against a detached minisign signature, passing its bytes on to
+// `sink` as they are read
+pub async fn verify_file_signature(
+    file_path: &Path,
+    signature: &str,
+    public_key: &str,
+    expected_file_name: &str,
+    sink: &mut dyn DownloadSink,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let public_key = decode_public_key(public_key)?;
+    let signature = decode_signature(signature)?;
+    let mut verifying = SignatureSink::new(&public_key, &signature, sink)?;
+    feed_file(file_path, &mut verifying).await?;
+    verifying.finish(expected_file_name)
+}
+
+// Path of the signature kept next to a cached archive
+pub fn get_signature_path(archive_path: &Path) -> PathBuf {
+    with_suffix(archive_path, ".minisig")
+}
+
+// The last path segment of a URL
+pub fn url_file_name(url: &str) -> Result<&str, Box<dyn std::error::Error>> {
+    url.split(['?', '#'])
+        .next()
+        .and_then(|u| u.rsplit('/').next())
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| format!("Invalid archive URL: {}", url).into())
+}
+
+// Download an archive and, when a public key is given, verify it against the
+// `.minisig` published next to it while it streams into `sink`. The signature is
+// kept next to the archive so a cached archive can be verified again. On
+// failure the archive is removed.
+pub async fn download_verified(
+    client: &HttpClient,
+    url: &str,
+    query: &str,
+    dest_path: &Path,
+    public_key: Option<&str>,
+    sink: &mut dyn DownloadSink,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let download_url = format!("{}{}", url, query);
+    let Some(public_key) = public_key else {
+        return download_file(client, &download_url, dest_path, sink).await;
+    };
+
+    let signature_text = fetch_signature(client, url).await?;
+    let public_key = decode_public_key(public_key)?;
+    let signature = decode_signature(&signature_text)?;
+    let mut verifying = SignatureSink::new(&public_key, &signature, sink)?;
+    download_file(client, &download_url, dest_path, &mut verifying).await?;
+
+    if let Err(e) = verifying.finish(url_file_name(url)?) {
+        let _ = std::fs::remove_file(dest_path);
+        return Err(e);
+    }
+    std::fs::write(get_signature_path(dest_path), signature_text)?;
     Ok(())
 }
@@ -0,0 +1,402 @@
+// In-process extraction of .tar.xz and .zip archives
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+
+// Map an archive entry path to a path relative to the destination, dropping the
+// first `strip` components. Returns Ok(None) for entries stripped away entirely.
+fn entry_path(path: &Path, strip: usize) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => components.push(part),
+            Component::CurDir => {}
+            _ => return Err(format!("Archive entry {} escapes the destination", path.display()).into()),
+        }
+    }
+
+    if components.len() <= strip {
+        return Ok(None);
+    }
+    Ok(Some(components[strip..].iter().collect()))
+}
+
+// Check that a symlink stays inside the destination: the target must be
+// relative and never climb above the destination root. `..` is only allowed at
+// the start, where it climbs real directories; after a name it would apply to
+// wherever that name resolves, which may be another symlink.
+fn check_symlink(link: &Path, target: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let escapes = || format!("Symlink {} -> {} escapes the destination", link.display(), target.display());
+
+    let mut depth = link.components().count().saturating_sub(1);
+    let mut descended = false;
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => {
+                depth += 1;
+                descended = true;
+            }
+            Component::CurDir => {}
+            Component::ParentDir if !descended => depth = depth.checked_sub(1).ok_or_else(escapes)?,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return Err(escapes().into()),
+        }
+    }
+    Ok(())
+}
+
+// Find the file a hard link copies, refusing sources that resolve outside the
+// destination through symlinks extracted earlier
+fn hard_link_source(dest_dir: &Path, source: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let outside = || format!("Hard link source {} is outside the destination", source.display());
+    let resolved = dest_dir.join(source).canonicalize().map_err(|_| outside())?;
+    if !resolved.starts_with(dest_dir.canonicalize()?) || !resolved.is_file() {
+        return Err(outside().into());
+    }
+    Ok(resolved)
+}
+
+// Prepare to create `relative` under `dest_dir`: refuse to write through a
+// symlink created by an earlier entry, and clear whatever is already there
+fn prepare_target(dest_dir: &Path, relative: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let mut current = dest_dir.to_path_buf();
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        current.push(component);
+        let Ok(metadata) = fs::symlink_metadata(&current) else {
+            continue;
+        };
+        if components.peek().is_none() {
+            if !metadata.is_dir() {
+                fs::remove_file(&current)?;
+            }
+        } else if metadata.file_type().is_symlink() {
+            return Err(format!("Archive entry {} is written through a symlink", relative.display()).into());
+        }
+    }
+
+    if let Some(parent) = current.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(current)
+}
+
+// Write a regular file, keeping the permission bits from the archive
+fn write_file(path: &Path, reader: &mut impl Read, mode: Option<u32>) -> io::Result<u64> {
+    let mut file = File::create(path)?;
+    let written = io::copy(reader, &mut file)?;
+    if let Some(mode) = mode {
+        file.set_permissions(fs::Permissions::from_mode(mode & 0o777))?;
+    }
+    Ok(written)
+}
+
+fn progress_bar(len: u64, template: &str) -> ProgressBar {
+    let pb = ProgressBar::new(len);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template(template)
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    pb
+}
+
+// Counts the compressed bytes consumed so progress can follow the archive size
+struct CountingReader<R> {
+    inner: R,
+    pb: ProgressBar,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pb.inc(n as u64);
+        Ok(n)
+    }
+}
+
+// Extract a tar.xz archive to a destination directory, dropping the first
+// `strip` path components of every entry
+pub fn extract_tarball(
+    archive_path: &Path,
+    dest_dir: &Path,
+    strip: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = File::open(archive_path)?;
+    let pb = progress_bar(
+        file.metadata()?.len(),
+        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} {msg}",
+    );
+    let reader = CountingReader { inner: BufReader::new(file), pb: pb.clone() };
+
+    let (entries, bytes) = unpack_tarball(reader, dest_dir, strip, &pb)?;
+    pb.finish_with_message(format!("Extracted {} entries ({})", entries, HumanBytes(bytes)));
+    Ok(())
+}
+
+// Unpack a tar.xz stream, reporting entries and bytes on `pb`. Returns the
+// number of entries and bytes extracted.
+pub fn unpack_tarball(
+    reader: impl Read,
+    dest_dir: &Path,
+    strip: usize,
+    pb: &ProgressBar,
+) -> Result<(u64, u64), Box<dyn std::error::Error>> {
+    let mut archive = tar::Archive::new(lzma_rust2::XzReader::new(reader, true));
+
+    fs::create_dir_all(dest_dir)?;
+    let mut entries = 0u64;
+    let mut bytes = 0u64;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let Some(relative) = entry_path(&path, strip)? else {
+            continue;
+        };
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            let target = prepare_target(dest_dir, &relative)?;
+            fs::create_dir_all(&target)?;
+        } else if entry_type.is_file() {
+            let mode = entry.header().mode().ok();
+            let target = prepare_target(dest_dir, &relative)?;
+            bytes += write_file(&target, &mut entry, mode)?;
+        } else if entry_type.is_symlink() {
+            let link_target = entry
+                .link_name()?
+                .ok_or(format!("Symlink {} has no target", path.display()))?
+                .into_owned();
+            check_symlink(&relative, &link_target)?;
+            let target = prepare_target(dest_dir, &relative)?;
+            symlink(&link_target, &target)?;
+        } else if entry_type.is_hard_link() {
+            // Hard links name another entry of the archive; copy it instead
+            let link_name = entry
+                .link_name()?
+                .ok_or(format!("Hard link {} has no target", path.display()))?
+                .into_owned();
+            let source = entry_path(&link_name, strip)?
+                .ok_or(format!("Hard link {} points outside the archive", path.display()))?;
+            let source = hard_link_source(dest_dir, &source)?;
+            let target = prepare_target(dest_dir, &relative)?;
+            fs::copy(source, &target)?;
+        } else {
+            // Devices, fifos and extension headers are never part of a toolchain
+            continue;
+        }
+
+        entries += 1;
+        pb.set_message(format!("{} entries, {} extracted", entries, HumanBytes(bytes)));
+    }
+
+    Ok((entries, bytes))
+}
+
+// Extract a zip archive to a destination directory, dropping the first
+// `strip` path components of every entry
+pub fn extract_zip(
+    archive_path: &Path,
+    dest_dir: &Path,
+    strip: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(File::open(archive_path)?))?;
+    let pb = progress_bar(
+        archive.len() as u64,
+        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} entries {msg}",
+    );
+
+    fs::create_dir_all(dest_dir)?;
+    let mut bytes = 0u64;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let path = PathBuf::from(file.name());
+        pb.inc(1);
+        let Some(relative) = entry_path(&path, strip)? else {
+            continue;
+        };
+
+        if file.is_dir() {
+            let target = prepare_target(dest_dir, &relative)?;
+            fs::create_dir_all(&target)?;
+        } else if file.is_symlink() {
+            let mut link_target = String::new();
+            file.read_to_string(&mut link_target)?;
+            let link_target = PathBuf::from(link_target);
+            check_symlink(&relative, &link_target)?;
+            let target = prepare_target(dest_dir, &relative)?;
+            symlink(&link_target, &target)?;
+        } else {
+            let mode = file.unix_mode();
+            let target = prepare_target(dest_dir, &relative)?;
+            bytes += write_file(&target, &mut file, mode)?;
+        }
+
+        pb.set_message(format!("({} extracted)", HumanBytes(bytes)));
+    }
+
+    pb.finish_with_message(format!("({} extracted)", HumanBytes(bytes)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // A fresh directory for one test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zpm-extract-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn tar_xz(build: impl FnOnce(&mut tar::Builder<Vec<u8>>)) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        build(&mut builder);
+        let tar = builder.into_inner().unwrap();
+        let mut xz = lzma_rust2::XzWriter::new(Vec::new(), lzma_rust2::XzOptions::with_preset(0)).unwrap();
+        xz.write_all(&tar).unwrap();
+        xz.finish().unwrap()
+    }
+
+    fn add_file(builder: &mut tar::Builder<Vec<u8>>, path: &str, contents: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, path, contents).unwrap();
+    }
+
+    fn add_link(builder: &mut tar::Builder<Vec<u8>>, kind: tar::EntryType, path: &str, target: &str) {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(kind);
+        header.set_size(0);
+        builder.append_link(&mut header, path, target).unwrap();
+    }
+
+    fn unpack(archive: &[u8], dest_dir: &Path) -> Result<(u64, u64), Box<dyn std::error::Error>> {
+        unpack_tarball(archive, dest_dir, 0, &ProgressBar::hidden())
+    }
+
+    #[test]
+    fn entry_path_rejects_parent_and_absolute_paths() {
+        for path in ["../x", "zig/../../x", "zig/..", "/etc/passwd"] {
+            assert!(entry_path(Path::new(path), 0).is_err(), "{} should be rejected", path);
+        }
+        assert_eq!(entry_path(Path::new("./zig/lib/std"), 1).unwrap(), Some(PathBuf::from("lib/std")));
+        assert_eq!(entry_path(Path::new("zig/"), 1).unwrap(), None);
+    }
+
+    #[test]
+    fn check_symlink_keeps_targets_inside() {
+        assert!(check_symlink(Path::new("lib/zig"), Path::new("../zig")).is_ok());
+        assert!(check_symlink(Path::new("a/b/c"), Path::new("../../d/e")).is_ok());
+        assert!(check_symlink(Path::new("zig"), Path::new("../zig")).is_err());
+        assert!(check_symlink(Path::new("lib/zig"), Path::new("../../zig")).is_err());
+        assert!(check_symlink(Path::new("zig"), Path::new("/usr/bin/zig")).is_err());
+        // `..` after a name applies to wherever the name resolves
+        assert!(check_symlink(Path::new("a/b/c"), Path::new("d/../..")).is_err());
+    }
+
+    #[test]
+    fn rejects_symlink_chains_that_climb_out() {
+        let dest_dir = temp_dir("chain");
+        // `d/up` -> `..` stays inside, but following it from `c` climbs out
+        let archive = tar_xz(|b| {
+            add_link(b, tar::EntryType::Symlink, "d/up", "..");
+            add_link(b, tar::EntryType::Symlink, "c", "d/up/../..");
+        });
+        assert!(unpack(&archive, &dest_dir).is_err());
+        assert!(fs::symlink_metadata(dest_dir.join("c")).is_err());
+        let _ = fs::remove_dir_all(&dest_dir);
+    }
+
+    #[test]
+    fn rejects_writes_through_an_extracted_symlink() {
+        let dest_dir = temp_dir("through");
+        let archive = tar_xz(|b| {
+            add_link(b, tar::EntryType::Symlink, "d/out", "..");
+            add_file(b, "d/out/evil", b"x");
+        });
+        assert!(unpack(&archive, &dest_dir).is_err());
+        assert!(!dest_dir.join("evil").exists());
+        let _ = fs::remove_dir_all(&dest_dir);
+    }
+
+    #[test]
+    fn hard_links_must_resolve_inside() {
+        let root = temp_dir("hardlink");
+        let dest_dir = root.join("dest");
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::write(root.join("secret"), b"secret").unwrap();
+        fs::write(dest_dir.join("file"), b"file").unwrap();
+        symlink(&root, dest_dir.join("escape")).unwrap();
+
+        assert!(hard_link_source(&dest_dir, Path::new("file")).is_ok());
+        assert!(hard_link_source(&dest_dir, Path::new("escape/secret")).is_err());
+        assert!(hard_link_source(&dest_dir, Path::new("missing")).is_err());
+        assert!(hard_link_source(&dest_dir, Path::new("escape")).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn copies_hard_links_inside_the_archive() {
+        let dest_dir = temp_dir("hardcopy");
+        let archive = tar_xz(|b| {
+            add_file(b, "zig/lib/a", b"same");
+            add_link(b, tar::EntryType::Link, "zig/lib/b", "zig/lib/a");
+        });
+        assert_eq!(unpack(&archive, &dest_dir).unwrap().0, 2);
+        assert_eq!(fs::read(dest_dir.join("zig/lib/b")).unwrap(), b"same");
+
+        let archive = tar_xz(|b| add_link(b, tar::EntryType::Link, "copy", "../../etc/passwd"));
+        assert!(unpack(&archive, &dest_dir).is_err());
+        assert!(!dest_dir.join("copy").exists());
+        let _ = fs::remove_dir_all(&dest_dir);
+    }
+
+    #[test]
+    fn prepare_target_replaces_files_but_not_through_symlinks() {
+        let dest_dir = temp_dir("prepare");
+        fs::write(dest_dir.join("file"), b"old").unwrap();
+        assert_eq!(prepare_target(&dest_dir, Path::new("file")).unwrap(), dest_dir.join("file"));
+        assert!(!dest_dir.join("file").exists());
+
+        assert!(prepare_target(&dest_dir, Path::new("a/b/c")).is_ok());
+        assert!(dest_dir.join("a/b").is_dir());
+
+        symlink(".", dest_dir.join("link")).unwrap();
+        assert!(prepare_target(&dest_dir, Path::new("link/file")).is_err());
+        let _ = fs::remove_dir_all(&dest_dir);
+    }
+
+    #[test]
+    fn zip_symlinks_are_checked() {
+        let root = temp_dir("zip");
+        let write_zip = |name: &str, links: &[(&str, &str)]| {
+            let path = root.join(name);
+            let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+            let options = zip::write::SimpleFileOptions::default();
+            zip.start_file("zig/zig", options).unwrap();
+            zip.write_all(b"zig").unwrap();
+            for (link, target) in links {
+                zip.add_symlink(*link, *target, options).unwrap();
+            }
+            zip.finish().unwrap();
+            path
+        };
+
+        let good = write_zip("good.zip", &[("zig/lib/zig", "../zig")]);
+        extract_zip(&good, &root.join("good"), 1).unwrap();
+        assert_eq!(fs::read_link(root.join("good/lib/zig")).unwrap(), PathBuf::from("../zig"));
+
+        let bad = write_zip("bad.zip", &[("zig/zig-link", "../../etc")]);
+        assert!(extract_zip(&bad, &root.join("bad"), 1).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
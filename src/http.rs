@@ -0,0 +1,151 @@
+use crate::config::Config;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, Response, StatusCode};
+use std::time::Duration;
+
+const USER_AGENT: &str = concat!("zpm/", env!("CARGO_PKG_VERSION"));
+
+// How failed requests are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    // Exponential backoff with jitter, stretched to honor a server's Retry-After
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        let jittered = exponential.mul_f64(0.5 + fastrand::f64() / 2.0);
+        match retry_after {
+            Some(retry_after) => retry_after.min(self.max_backoff).max(jittered),
+            None => jittered,
+        }
+    }
+}
+
+// The HTTP client every network operation goes through, with timeouts and retries
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    policy: RetryPolicy,
+}
+
+// Whether a transport error is worth retrying. Malformed responses and invalid
+// requests fail the same way every time, so only network failures are.
+pub fn is_transient(error: &reqwest::Error) -> bool {
+    if error.is_timeout() || error.is_connect() || error.is_body() {
+        return true;
+    }
+    // Response::chunk reports a failed body read as a decode error wrapping it
+    error.is_decode()
+        && std::error::Error::source(error)
+            .and_then(|source| source.downcast_ref::<reqwest::Error>())
+            .is_some_and(is_transient)
+}
+
+// Describe an error along with its sources, which carry the useful detail
+pub fn describe_error(error: &dyn std::error::Error) -> String {
+    let mut description = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        description.push_str(": ");
+        description.push_str(&e.to_string());
+        source = e.source();
+    }
+    description
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+// Retry-After in its delay-seconds form; HTTP dates fall back to the normal backoff
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+impl HttpClient {
+    pub fn new(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(Duration::from_secs(config.connect_timeout))
+            .read_timeout(Duration::from_secs(config.read_timeout))
+            .build()?;
+
+        Ok(HttpClient {
+            client,
+            policy: RetryPolicy {
+                max_attempts: config.retry_attempts.max(1),
+                initial_backoff: Duration::from_millis(config.retry_backoff_ms),
+                max_backoff: Duration::from_millis(config.retry_max_backoff_ms),
+            },
+        })
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    // Report a failed attempt and wait before the next one
+    pub async fn backoff(&self, url: &str, attempt: u32, reason: &str, retry_after: Option<Duration>) {
+        let delay = self.policy.delay(attempt, retry_after);
+        println!(
+            "Warning: attempt {}/{} for {} failed: {}; retrying in {:.1}s",
+            attempt,
+            self.policy.max_attempts,
+            url,
+            reason,
+            delay.as_secs_f64()
+        );
+        tokio::time::sleep(delay).await;
+    }
+
+    pub async fn get(&self, url: &str) -> Result<Response, Box<dyn std::error::Error>> {
+        self.get_with_headers(url, HeaderMap::new()).await
+    }
+
+    // Send a GET request, retrying transport errors, 5xx and 429 responses.
+    // Other statuses are returned for the caller to handle.
+    pub async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+    ) -> Result<Response, Box<dyn std::error::Error>> {
+        let mut attempt = 1;
+        loop {
+            let result = self.client.get(url).headers(headers.clone()).send().await;
+
+            let (reason, wait) = match result {
+                Ok(response) if is_transient_status(response.status()) => {
+                    (format!("HTTP {}", response.status()), retry_after(&response))
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if is_transient(&e) => (describe_error(&e), None),
+                Err(e) => return Err(format!("Request to {} failed: {}", url, describe_error(&e)).into()),
+            };
+
+            if attempt >= self.policy.max_attempts {
+                return Err(format!(
+                    "Request to {} failed on attempt {}/{}: {}",
+                    url, attempt, self.policy.max_attempts, reason
+                )
+                .into());
+            }
+            self.backoff(url, attempt, &reason, wait).await;
+            attempt += 1;
+        }
+    }
+}
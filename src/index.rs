@@ -0,0 +1,141 @@
+use crate::config::load_config;
+use crate::http::HttpClient;
+use crate::models::ReleaseIndex;
+use crate::utils::get_cache_dir;
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const INDEX_URL: &str = "https://ziglang.org/download/index.json";
+
+const INDEX_FILE: &str = "index.json";
+const INDEX_METADATA_FILE: &str = "index.meta.json";
+
+// Validators and fetch time for the cached index
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct IndexMetadata {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: u64,
+}
+
+fn get_index_file(home_dir: &str) -> PathBuf {
+    get_cache_dir(home_dir).join(INDEX_FILE)
+}
+
+fn get_index_metadata_file(home_dir: &str) -> PathBuf {
+    get_cache_dir(home_dir).join(INDEX_METADATA_FILE)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn read_metadata(home_dir: &str) -> IndexMetadata {
+    std::fs::read_to_string(get_index_metadata_file(home_dir))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn write_metadata(home_dir: &str, metadata: &IndexMetadata) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(get_index_metadata_file(home_dir), serde_json::to_string(metadata)?)?;
+    Ok(())
+}
+
+// Read the cached index, if there is a valid one
+fn read_cached_index(home_dir: &str) -> Option<ReleaseIndex> {
+    let contents = std::fs::read_to_string(get_index_file(home_dir)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+// Fetch the index, revalidating the cached copy with its ETag/Last-Modified
+async fn fetch_remote_index(
+    client: &HttpClient,
+    home_dir: &str,
+    cached: Option<ReleaseIndex>,
+) -> Result<ReleaseIndex, Box<dyn std::error::Error>> {
+    let metadata = read_metadata(home_dir);
+
+    let mut headers = HeaderMap::new();
+    if cached.is_some() {
+        if let Some(etag) = &metadata.etag {
+            headers.insert(IF_NONE_MATCH, etag.parse()?);
+        }
+        if let Some(last_modified) = &metadata.last_modified {
+            headers.insert(IF_MODIFIED_SINCE, last_modified.parse()?);
+        }
+    }
+    let response = client.get_with_headers(INDEX_URL, headers).await?;
+
+    if response.status() == StatusCode::NOT_MODIFIED
+        && let Some(cached) = cached
+    {
+        write_metadata(home_dir, &IndexMetadata { fetched_at: now(), ..metadata })?;
+        return Ok(cached);
+    }
+
+    let response = response.error_for_status()?;
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let metadata = IndexMetadata {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+        fetched_at: now(),
+    };
+
+    let body = response.text().await?;
+    let releases: ReleaseIndex = serde_json::from_str(&body)
+        .map_err(|e| format!("Invalid release index from {}: {}", INDEX_URL, e))?;
+
+    std::fs::create_dir_all(get_cache_dir(home_dir))?;
+    std::fs::write(get_index_file(home_dir), &body)?;
+    write_metadata(home_dir, &metadata)?;
+
+    Ok(releases)
+}
+
+// Get the release index. A cached copy younger than the configured TTL is used
+// as-is; otherwise it is revalidated, and used stale if the network is down.
+// In offline mode only the cache is consulted.
+pub async fn fetch_index(
+    client: &HttpClient,
+    home_dir: &str,
+    offline: bool,
+) -> Result<ReleaseIndex, Box<dyn std::error::Error>> {
+    let cached = read_cached_index(home_dir);
+
+    if offline {
+        return cached.ok_or_else(|| {
+            "No cached release index; run the command once without --offline".into()
+        });
+    }
+
+    let config = load_config(home_dir)?;
+    let age = now().saturating_sub(read_metadata(home_dir).fetched_at);
+    if age < config.index_ttl
+        && let Some(cached) = cached
+    {
+        return Ok(cached);
+    }
+
+    let has_cache = cached.is_some();
+    match fetch_remote_index(client, home_dir, cached).await {
+        Ok(releases) => Ok(releases),
+        Err(e) if has_cache => {
+            println!("Warning: failed to refresh release index ({}), using cached copy", e);
+            read_cached_index(home_dir).ok_or(e)
+        }
+        Err(e) => Err(format!("Failed to fetch release index: {}", e).into()),
+    }
+}
@@ -0,0 +1,50 @@
+// Catch Ctrl-C only while an operation that cleans up after itself is running.
+// tokio's ctrl_c() replaces the SIGINT disposition for the life of the process,
+// which would leave zpm (and a bisect waiting on a test) impossible to interrupt.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// How often a waiting task checks for Ctrl-C
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+// Restores the previous SIGINT disposition when dropped
+pub struct InterruptGuard {
+    previous: libc::sigaction,
+}
+
+pub fn catch_interrupts() -> InterruptGuard {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_sigint as *const () as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        libc::sigaction(libc::SIGINT, &action, &mut previous);
+        InterruptGuard { previous }
+    }
+}
+
+impl InterruptGuard {
+    // Resolves once Ctrl-C is pressed
+    pub async fn interrupted(&self) {
+        while !INTERRUPTED.load(Ordering::SeqCst) {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        // SAFETY: restores the disposition saved in catch_interrupts
+        unsafe {
+            libc::sigaction(libc::SIGINT, &self.previous, std::ptr::null_mut());
+        }
+    }
+}
@@ -1,10 +1,28 @@
 // Main library file defining the module structure
 
+pub mod cache;
 pub mod commands;
+pub mod config;
 pub mod download;
+pub mod extract;
+pub mod http;
+pub mod index;
+pub mod interrupt;
+pub mod lock;
+pub mod mirrors;
 pub mod models;
+pub mod pipeline;
+pub mod project;
+pub mod record;
+pub mod shim;
 pub mod utils;
+pub mod version;
+pub mod version_req;
+pub mod zon;
 
 // Re-export common types and functions for easier access
 pub use models::{PlatformEntry, ReleaseIndex, VersionEntry};
-pub use utils::{file_exists, get_cache_dir, get_current_file, get_local_bin_dir, get_platform_string, get_version_dir, get_versions_dir, get_zig_binary, get_zig_symlink, get_zpm_dir, version_compare};
+pub use project::{resolve_active_version, ActiveVersion, VersionSource};
+pub use utils::{file_exists, get_cache_dir, get_current_file, get_installed_versions, get_local_bin_dir, get_platform_string, get_version_dir, get_versions_dir, get_zig_binary, get_zig_symlink, get_zls_dir, get_zls_symlink, get_zpm_dir};
+pub use version::ZigVersion;
+pub use version_req::VersionReq;
@@ -0,0 +1,33 @@
+// An advisory lock serializing commands that change ~/.zpm
+use crate::utils::get_zpm_dir;
+use std::fs::{File, OpenOptions, TryLockError};
+
+const LOCK_FILE: &str = "lock";
+
+// Held until dropped; the OS releases it if the process dies
+pub struct ZpmLock {
+    _file: File,
+}
+
+// Take the lock, waiting for any other zpm process that holds it
+pub fn lock_zpm(home_dir: &str) -> Result<ZpmLock, Box<dyn std::error::Error>> {
+    let zpm_dir = get_zpm_dir(home_dir);
+    std::fs::create_dir_all(&zpm_dir)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(zpm_dir.join(LOCK_FILE))?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            // stderr, since a shim or `exec` may take the lock before running a tool
+            eprintln!("Waiting for another zpm process to finish...");
+            file.lock()?;
+        }
+        Err(TryLockError::Error(e)) => return Err(format!("Failed to lock {}: {}", zpm_dir.display(), e).into()),
+    }
+
+    Ok(ZpmLock { _file: file })
+}
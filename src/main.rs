@@ -1,15 +1,24 @@
 use clap::{Parser, Subcommand};
-use reqwest::Client;
 use std::env;
+use std::time::Duration;
 use tokio::fs::create_dir_all;
+use zpm::cache::{parse_duration, parse_size, CleanPolicy};
 use zpm::commands::*;
+use zpm::config::load_config;
+use zpm::http::HttpClient;
+use zpm::lock::lock_zpm;
+use zpm::shim::{run_shim, shim_from_argv0};
 use zpm::utils::{get_cache_dir, get_versions_dir};
+use zpm::version::ZigVersion;
+use zpm::version_req::VersionReq;
 
 #[derive(Parser, Debug)]
 #[command(name = "zpm")]
 #[command(about = "Zig Package Manager")]
 #[command(long_about = None)]
 struct Cli {
+    #[arg(long, global = true, help = "Use only the cached release index and archives; never touch the network")]
+    offline: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -19,21 +28,23 @@ enum Commands {
     #[command(about = "Install a Zig version")]
     #[command(alias = "i")]
     Install {
-        #[arg(help = "Version to install (latest, master, stable, or specific version)")]
-        version: Option<String>,
+        #[arg(help = "Version or range to install (latest-stable, nightly, 0.13, ~0.12, \">=0.12 <0.14\", or an exact version); defaults to the project's version")]
+        version: Option<VersionReq>,
         #[arg(long, short, help = "Set as default version")]
         default: bool,
+        #[arg(long, help = "Install without verifying the archive's minisign signature (unsafe)")]
+        skip_signature: bool,
     },
     #[command(about = "Uninstall a Zig version")]
     #[command(alias = "rm")]
     Uninstall {
-        #[arg(help = "Version to uninstall")]
-        version: String,
+        #[arg(help = "Version or range to uninstall; the newest installed match is removed")]
+        version: VersionReq,
     },
     #[command(about = "Set a version as default")]
     Use {
-        #[arg(help = "Version to use")]
-        version: String,
+        #[arg(help = "Version or range to use; the newest installed match is selected")]
+        version: VersionReq,
     },
     #[command(about = "List installed versions")]
     #[command(alias = "ls")]
@@ -41,15 +52,137 @@ enum Commands {
         #[arg(long, short, help = "List available versions")]
         remote: bool,
     },
-    #[command(about = "Install ZLS for the current Zig version")]
+    #[command(about = "Install ZLS for the current Zig version (same as `zls install`)")]
     InstallZls {},
+    #[command(about = "Manage ZLS installs")]
+    Zls {
+        #[command(subcommand)]
+        command: ZlsCommands,
+    },
+    #[command(about = "Run a command under a specific Zig version")]
+    #[command(alias = "run")]
+    Exec {
+        #[arg(help = "Version or range to use (latest-stable, nightly, 0.13, ~0.12, or an exact version)")]
+        version: VersionReq,
+        #[arg(long, help = "Install the version if it is missing")]
+        install: bool,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true, help = "Command to run")]
+        command: Vec<String>,
+    },
+    #[command(about = "Find the first nightly build that breaks a command")]
+    Bisect {
+        #[command(subcommand)]
+        command: BisectCommands,
+    },
+    #[command(about = "Install zig/zls shims that follow the project's version")]
+    Shims {
+        #[arg(long, help = "Remove the shims and restore the default symlink")]
+        remove: bool,
+    },
+    #[command(about = "Point an editor or ZLS at the managed Zig and ZLS")]
+    EditorConfig {
+        #[arg(help = "What to configure: vscode, nvim, helix, zed or zls")]
+        editor: Editor,
+        #[arg(long, help = "Use the active version's binaries instead of the ~/.local/bin links")]
+        pin: bool,
+        #[arg(long, help = "Installed Zig version or range to pin (implies --pin)")]
+        zig: Option<VersionReq>,
+        #[arg(long, help = "Write the project's config files instead of the user's")]
+        project: bool,
+        #[arg(long, help = "Print the updated config instead of writing it")]
+        print: bool,
+    },
+    #[command(about = "Manage the download cache")]
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BisectCommands {
+    #[command(about = "Bisect between a good and a bad dev version")]
+    Run {
+        #[arg(help = "Known-good dev version")]
+        good: ZigVersion,
+        #[arg(help = "Known-bad dev version")]
+        bad: ZigVersion,
+        #[arg(long, help = "Remove builds installed during bisect when done")]
+        prune: bool,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, help = "Command to run (default: zig build test)")]
+        command: Vec<String>,
+    },
+    #[command(about = "Remove builds installed during bisect")]
+    Prune {},
+}
+
+#[derive(Subcommand, Debug)]
+enum ZlsCommands {
+    #[command(about = "List installed Zig versions and their ZLS")]
+    Ls {},
+    #[command(about = "Install ZLS for a Zig version")]
+    Install {
+        #[arg(help = "Installed Zig version or range; defaults to the active version")]
+        version: Option<VersionReq>,
+    },
+    #[command(about = "Remove the ZLS installed for a Zig version")]
+    Uninstall {
+        #[arg(help = "Installed Zig version or range; defaults to the active version")]
+        version: Option<VersionReq>,
+    },
+    #[command(about = "Build ZLS from source with an installed Zig version")]
+    Build {
+        #[arg(help = "ZLS checkout, source archive (.tar.xz or .zip), archive URL, or cached archive name or SHA-256 prefix")]
+        source: String,
+        #[arg(long, help = "Installed Zig version or range to build with; defaults to the active version")]
+        zig: Option<VersionReq>,
+        #[arg(long, help = "Expected SHA-256 of the source archive")]
+        sha256: Option<String>,
+    },
+    #[command(about = "Print the path of the ZLS binary for a Zig version")]
+    Which {
+        #[arg(help = "Installed Zig version or range; defaults to the active version")]
+        version: Option<VersionReq>,
+    },
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+#[derive(Subcommand, Debug)]
+enum CacheCommands {
+    #[command(about = "List cached archives")]
+    Ls {},
+    #[command(about = "Show the total size of the cache")]
+    Size {},
+    #[command(about = "Rehash cached archives and remove corrupt ones")]
+    Verify {},
+    #[command(about = "Remove cached archives; with no options, empty the cache")]
+    Clean {
+        #[arg(long, value_parser = parse_duration, help = "Remove archives not used for this long (e.g. 30d, 12h, 2w)")]
+        older_than: Option<Duration>,
+        #[arg(long, help = "Remove archives no installed version was installed from")]
+        keep_installed_only: bool,
+        #[arg(long, value_parser = parse_size, help = "Evict least recently used archives until the cache fits (e.g. 2G)")]
+        max_size: Option<u64>,
+    },
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Invoked as `zig` or `zls`: dispatch without starting the async runtime
+    let mut args = env::args_os();
+    if let Some(shim) = args.next().as_deref().and_then(shim_from_argv0) {
+        let home_dir = env::var("HOME").expect("HOME environment variable not set");
+        return run_shim(shim, &home_dir, args.collect());
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run())
+}
+
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
-    let client = Client::new();
     let home_dir = env::var("HOME").expect("HOME environment variable not set");
+    let client = HttpClient::new(&load_config(&home_dir)?)?;
     let versions_dir = get_versions_dir(&home_dir);
     let cache_dir = get_cache_dir(&home_dir);
 
@@ -57,28 +190,110 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     create_dir_all(&versions_dir).await?;
     create_dir_all(&cache_dir).await?;
 
+    // These always need the network
+    if cli.offline
+        && matches!(
+            cli.command,
+            Commands::InstallZls {}
+                | Commands::Zls { command: ZlsCommands::Install { .. } }
+                | Commands::Bisect { command: BisectCommands::Run { .. } }
+        )
+    {
+        return Err("This command needs network access and cannot run with --offline".into());
+    }
+
+    // Commands that change installs, symlinks or `current` run one at a time
+    let _lock = match cli.command {
+        Commands::Install { .. }
+        | Commands::Uninstall { .. }
+        | Commands::Use { .. }
+        | Commands::InstallZls {}
+        | Commands::Zls { command: ZlsCommands::Install { .. } | ZlsCommands::Uninstall { .. } | ZlsCommands::Build { .. } }
+        | Commands::Bisect { .. }
+        | Commands::Shims { .. }
+        | Commands::Cache { command: CacheCommands::Verify {} | CacheCommands::Clean { .. } } => {
+            Some(lock_zpm(&home_dir)?)
+        }
+        Commands::List { .. }
+        | Commands::Exec { .. }
+        | Commands::Zls { command: ZlsCommands::Ls {} | ZlsCommands::Which { .. } }
+        | Commands::EditorConfig { .. }
+        | Commands::Cache { command: CacheCommands::Ls {} | CacheCommands::Size {} } => None,
+    };
+
     match &cli.command {
-        Commands::Install { version, default } => {
-            let version = version.as_deref().unwrap_or("latest");
-            install(&client, &home_dir, version, *default).await?;
+        Commands::Install { version, default, skip_signature } => {
+            let options = InstallOptions {
+                set_as_default: *default,
+                offline: cli.offline,
+                skip_signature: *skip_signature,
+                ..Default::default()
+            };
+            install(&client, &home_dir, version.as_ref(), &options).await?;
         }
         Commands::Uninstall { version } => {
             uninstall(&home_dir, version).await?;
         }
         Commands::Use { version } => {
-            set_default(&home_dir, version).await?;
+            use_version(&home_dir, version).await?;
         }
         Commands::List { remote } => {
             if *remote {
                 // For compatibility, keep the remote option working
-                list_versions(&client, &home_dir).await?;
+                list_versions(&client, &home_dir, cli.offline).await?;
             } else {
-                list_versions(&client, &home_dir).await?;
+                list_versions(&client, &home_dir, cli.offline).await?;
             }
         }
         Commands::InstallZls {} => {
-            install_zls(&client, &home_dir).await?;
+            install_zls(&client, &home_dir, None).await?;
         }
+        Commands::Zls { command } => match command {
+            ZlsCommands::Ls {} => zls_ls(&home_dir).await?,
+            ZlsCommands::Install { version } => install_zls(&client, &home_dir, version.as_ref()).await?,
+            ZlsCommands::Uninstall { version } => zls_uninstall(&home_dir, version.as_ref()).await?,
+            ZlsCommands::Build { source, zig, sha256 } => {
+                zls_build(&client, &home_dir, source, zig.as_ref(), sha256.as_deref(), cli.offline).await?;
+            }
+            ZlsCommands::Which { version } => zls_which(&home_dir, version.as_ref()).await?,
+        },
+        Commands::Exec { version, install, command } => {
+            let code = exec(&client, &home_dir, version, *install, cli.offline, command).await?;
+            std::process::exit(code);
+        }
+        Commands::Bisect { command } => match command {
+            BisectCommands::Run { good, bad, prune, command } => {
+                bisect(&client, &home_dir, good, bad, command, *prune).await?;
+            }
+            BisectCommands::Prune {} => {
+                prune_bisect_installs(&home_dir).await?;
+            }
+        },
+        Commands::Shims { remove } => {
+            shims(&home_dir, *remove).await?;
+        }
+        Commands::EditorConfig { editor, pin, zig, project, print } => {
+            let options = EditorConfigOptions {
+                pin: *pin,
+                zig: zig.clone(),
+                project: *project,
+                print: *print,
+            };
+            editor_config(&home_dir, *editor, &options).await?;
+        }
+        Commands::Cache { command } => match command {
+            CacheCommands::Ls {} => cache_ls(&home_dir).await?,
+            CacheCommands::Size {} => cache_size(&home_dir).await?,
+            CacheCommands::Verify {} => cache_verify(&home_dir).await?,
+            CacheCommands::Clean { older_than, keep_installed_only, max_size } => {
+                let policy = CleanPolicy {
+                    older_than: *older_than,
+                    keep_installed_only: *keep_installed_only,
+                    max_size: *max_size,
+                };
+                cache_clean(&home_dir, &policy).await?;
+            }
+        },
     }
 
     Ok(())
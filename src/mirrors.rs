@@ -0,0 +1,107 @@
+// Community mirrors of ziglang.org/download and ziglang.org/builds
+use crate::config::Config;
+use crate::download::{download_verified, sink_println, url_file_name, DownloadSink};
+use crate::http::HttpClient;
+use crate::utils::get_cache_dir;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const COMMUNITY_MIRRORS_URL: &str = "https://ziglang.org/download/community-mirrors.txt";
+const MIRRORS_FILE: &str = "community-mirrors.txt";
+
+// Used when the community list cannot be fetched and has never been cached
+const BUILTIN_MIRRORS: [&str; 4] = [
+    "https://pkg.machengine.org/zig",
+    "https://zigmirror.hryx.net/zig",
+    "https://zig.linus.dev/zig",
+    "https://zig.florent.dev",
+];
+
+fn get_mirrors_file(home_dir: &str) -> PathBuf {
+    get_cache_dir(home_dir).join(MIRRORS_FILE)
+}
+
+// One mirror URL per line; blank lines and comments are skipped
+fn parse_mirrors(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter(|l| l.starts_with("https://"))
+        .map(|l| l.trim_end_matches('/').to_string())
+        .collect()
+}
+
+fn read_cached_mirrors(home_dir: &str, max_age: Option<Duration>) -> Option<Vec<String>> {
+    let path = get_mirrors_file(home_dir);
+    if let Some(max_age) = max_age {
+        let age = std::fs::metadata(&path).ok()?.modified().ok()?.elapsed().ok()?;
+        if age > max_age {
+            return None;
+        }
+    }
+    let mirrors = parse_mirrors(&std::fs::read_to_string(path).ok()?);
+    (!mirrors.is_empty()).then_some(mirrors)
+}
+
+async fn fetch_community_mirrors(client: &HttpClient, home_dir: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let contents = client.get(COMMUNITY_MIRRORS_URL).await?.error_for_status()?.text().await?;
+    let mirrors = parse_mirrors(&contents);
+    if mirrors.is_empty() {
+        return Err("Community mirror list is empty".into());
+    }
+    std::fs::create_dir_all(get_cache_dir(home_dir))?;
+    std::fs::write(get_mirrors_file(home_dir), contents)?;
+    Ok(mirrors)
+}
+
+// The mirrors to try, in shuffled order. A `mirrors` list in the config is used
+// as-is; otherwise the community list is fetched and cached like the release index.
+pub async fn load_mirrors(client: &HttpClient, home_dir: &str, config: &Config) -> Vec<String> {
+    let mut mirrors = match &config.mirrors {
+        Some(mirrors) => mirrors.iter().map(|m| m.trim_end_matches('/').to_string()).collect(),
+        None => match read_cached_mirrors(home_dir, Some(Duration::from_secs(config.index_ttl))) {
+            Some(mirrors) => mirrors,
+            None => match fetch_community_mirrors(client, home_dir).await {
+                Ok(mirrors) => mirrors,
+                Err(e) => {
+                    println!("Warning: failed to fetch community mirror list ({})", e);
+                    read_cached_mirrors(home_dir, None)
+                        .unwrap_or_else(|| BUILTIN_MIRRORS.iter().map(|m| m.to_string()).collect())
+                }
+            },
+        },
+    };
+
+    fastrand::shuffle(&mut mirrors);
+    mirrors
+}
+
+// Download an archive from the mirrors, falling back to its origin URL, and
+// return where it came from. Mirrors are only used when a public key is given,
+// since an unverified archive from a third party cannot be trusted.
+pub async fn download_with_mirrors(
+    client: &HttpClient,
+    mirrors: &[String],
+    origin_url: &str,
+    dest_path: &Path,
+    public_key: Option<&str>,
+    sink: &mut dyn DownloadSink,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let file_name = url_file_name(origin_url)?;
+
+    if public_key.is_some() {
+        for mirror in mirrors {
+            sink_println(sink, &format!("Downloading {} from mirror {}...", file_name, mirror));
+            let url = format!("{}/{}", mirror, file_name);
+            match download_verified(client, &url, "?source=zpm", dest_path, public_key, sink).await {
+                Ok(()) => return Ok(mirror.clone()),
+                Err(e) => sink_println(sink, &format!("Warning: mirror {} failed: {}", mirror, e)),
+            }
+        }
+    }
+
+    sink_println(sink, &format!("Downloading {}...", origin_url));
+    download_verified(client, origin_url, "", dest_path, public_key, sink).await?;
+    Ok(origin_url.to_string())
+}
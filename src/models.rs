@@ -1,3 +1,4 @@
+use crate::version::ZigVersion;
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
@@ -23,6 +24,20 @@ pub struct ReleaseIndex {
     pub versions: std::collections::HashMap<String, VersionEntry>,
 }
 
+impl ReleaseIndex {
+    // Tagged releases in the index, skipping `master` and unparsable keys
+    pub fn releases(&self) -> impl Iterator<Item = (ZigVersion, &VersionEntry)> {
+        self.versions
+            .iter()
+            .filter_map(|(k, v)| k.parse::<ZigVersion>().ok().filter(|k| !k.is_master()).map(|k| (k, v)))
+    }
+
+    // The concrete dev version of the index's master entry
+    pub fn master_version(&self) -> Option<ZigVersion> {
+        self.versions.get("master")?.version.as_deref()?.parse().ok()
+    }
+}
+
 // ZLS Release models
 #[derive(Deserialize, Debug)]
 pub struct ZlsAsset {
@@ -32,5 +47,27 @@ pub struct ZlsAsset {
 
 #[derive(Deserialize, Debug)]
 pub struct ZlsRelease {
+    pub tag_name: String,
     pub assets: Vec<ZlsAsset>,
 }
+
+// Answer of the zigtools select-version endpoint: a release entry shaped like
+// those in the Zig index, or an error when no compatible build exists
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ZlsSelectResponse {
+    Error { code: i64, message: String },
+    Release(VersionEntry),
+}
+
+// GitHub compare models used by bisect
+#[derive(Deserialize, Debug)]
+pub struct GithubCommit {
+    pub sha: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GithubCompare {
+    pub total_commits: usize,
+    pub commits: Vec<GithubCommit>,
+}
@@ -0,0 +1,184 @@
+// Hash and extract an archive while it downloads
+use crate::download::{DownloadSink, SinkWrite};
+use crate::extract::unpack_tarball;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+// Chunks buffered between the download and the extractor
+const CHANNEL_DEPTH: usize = 64;
+
+// Reads the chunks sent over a channel as one stream
+struct ChannelReader {
+    receiver: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            // Runs on the extraction thread, outside the async runtime
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                // The sender is gone: end of stream
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+struct Extractor {
+    sender: Sender<Vec<u8>>,
+    handle: JoinHandle<Result<(u64, u64), String>>,
+    pb: ProgressBar,
+}
+
+impl Extractor {
+    fn wait(self) -> Result<(u64, u64), String> {
+        drop(self.sender);
+        let result = self
+            .handle
+            .join()
+            .unwrap_or_else(|_| Err("Extraction thread panicked".to_string()));
+        self.pb.finish_and_clear();
+        result
+    }
+}
+
+// Computes the archive's SHA-256 and unpacks it into a staging directory on a
+// separate thread as bytes arrive. The staged tree is removed unless committed.
+pub struct ExtractPipeline {
+    staging_dir: PathBuf,
+    strip: usize,
+    hasher: Sha256,
+    extractor: Option<Extractor>,
+    // Holds the download bar and the extraction spinner below it
+    progress: MultiProgress,
+    committed: bool,
+}
+
+impl ExtractPipeline {
+    pub fn new(staging_dir: PathBuf, strip: usize) -> Self {
+        ExtractPipeline {
+            staging_dir,
+            strip,
+            hasher: Sha256::new(),
+            extractor: None,
+            progress: MultiProgress::new(),
+            committed: false,
+        }
+    }
+
+    pub fn staging_dir(&self) -> &Path {
+        &self.staging_dir
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        remove_dir_if_exists(&self.staging_dir)?;
+        std::fs::create_dir_all(&self.staging_dir)?;
+
+        let pb = self.progress.add(ProgressBar::new_spinner());
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.green} [{elapsed_precise}] Extracting: {msg}")
+                .unwrap(),
+        );
+
+        let (sender, receiver) = channel(CHANNEL_DEPTH);
+        let reader = ChannelReader { receiver, chunk: Vec::new(), pos: 0 };
+        let staging_dir = self.staging_dir.clone();
+        let strip = self.strip;
+        let thread_pb = pb.clone();
+        let handle = std::thread::spawn(move || {
+            let mut reader = reader;
+            let result = unpack_tarball(&mut reader, &staging_dir, strip, &thread_pb)
+                .map_err(|e| e.to_string());
+            // Drain the rest so the download never blocks on a failed extraction
+            let _ = io::copy(&mut reader, &mut io::sink());
+            result
+        });
+
+        self.extractor = Some(Extractor { sender, handle, pb });
+        Ok(())
+    }
+
+    // Wait for extraction to finish, returning the archive's SHA-256 and the
+    // number of entries and bytes extracted
+    pub fn finish(&mut self) -> Result<(String, u64, u64), Box<dyn std::error::Error>> {
+        let extractor = self.extractor.take().ok_or("Nothing was downloaded")?;
+        let (entries, bytes) = extractor
+            .wait()
+            .map_err(|e| format!("Failed to extract archive: {}", e))?;
+        let shasum = format!("{:x}", std::mem::take(&mut self.hasher).finalize());
+        Ok((shasum, entries, bytes))
+    }
+
+    // Move the staged tree into place
+    pub fn commit(mut self, dest_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if self.extractor.is_some() {
+            return Err("Extraction has not finished".into());
+        }
+        std::fs::rename(&self.staging_dir, dest_dir)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+fn remove_dir_if_exists(path: &Path) -> io::Result<()> {
+    match std::fs::remove_dir_all(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+impl DownloadSink for ExtractPipeline {
+    fn restart(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(extractor) = self.extractor.take() {
+            let _ = extractor.wait();
+        }
+        remove_dir_if_exists(&self.staging_dir)?;
+        self.hasher = Sha256::new();
+        Ok(())
+    }
+
+    fn write<'a>(&'a mut self, chunk: &'a [u8]) -> SinkWrite<'a> {
+        Box::pin(async move {
+            if self.extractor.is_none() {
+                self.start()?;
+            }
+            self.hasher.update(chunk);
+            if let Some(extractor) = &self.extractor {
+                // A failed send means the extractor already stopped; its error is
+                // reported by finish
+                let _ = extractor.sender.send(chunk.to_vec()).await;
+            }
+            Ok(())
+        })
+    }
+
+    fn progress(&self) -> Option<&MultiProgress> {
+        Some(&self.progress)
+    }
+}
+
+impl Drop for ExtractPipeline {
+    fn drop(&mut self) {
+        if let Some(extractor) = self.extractor.take() {
+            let _ = extractor.wait();
+        }
+        if !self.committed {
+            let _ = remove_dir_if_exists(&self.staging_dir);
+        }
+    }
+}
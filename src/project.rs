@@ -0,0 +1,308 @@
+use crate::utils::{file_exists, get_current_file, read_master_alias};
+use crate::version::ZigVersion;
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ZIG_VERSION_FILE: &str = ".zig-version";
+const TOOL_VERSIONS_FILE: &str = ".tool-versions";
+const PROJECT_FILE: &str = "zpm.json";
+const BUILD_ZON_FILE: &str = "build.zig.zon";
+
+// Environment variable overriding the active version
+pub const VERSION_ENV: &str = "ZPM_ZIG_VERSION";
+
+// Where the active Zig version was resolved from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionSource {
+    Environment,
+    ZigVersionFile(PathBuf),
+    ToolVersions(PathBuf),
+    ProjectFile(PathBuf),
+    Default,
+}
+
+impl fmt::Display for VersionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionSource::Environment => write!(f, "set by {}", VERSION_ENV),
+            VersionSource::ZigVersionFile(path)
+            | VersionSource::ToolVersions(path)
+            | VersionSource::ProjectFile(path) => write!(f, "set by {}", path.display()),
+            VersionSource::Default => write!(f, "global default"),
+        }
+    }
+}
+
+// The Zig version in effect for a directory
+#[derive(Debug, Clone)]
+pub struct ActiveVersion {
+    pub version: ZigVersion,
+    pub source: VersionSource,
+}
+
+// zpm project file (zpm.json)
+#[derive(Deserialize, Debug)]
+struct ProjectFile {
+    zig: Option<String>,
+}
+
+// Read a .zig-version file: the first non-empty, non-comment line
+fn read_zig_version_file(path: &Path) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    Ok(contents
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string()))
+}
+
+// Read the `zig` entry of an asdf-style .tool-versions file
+fn read_tool_versions(path: &Path) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut fields = line.split_whitespace();
+        if fields.next() == Some("zig") {
+            // asdf allows fallback versions after the first; only the first is used
+            return Ok(fields.next().map(|v| v.to_string()));
+        }
+    }
+
+    Ok(None)
+}
+
+// Read the `zig` field of a zpm.json project file
+fn read_project_file(path: &Path) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let project: ProjectFile = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid project file {}: {}", path.display(), e))?;
+
+    Ok(project.zig.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()))
+}
+
+// Parse a version read from a file, naming the file on failure
+fn parse_pinned_version(version: &str, path: &Path) -> Result<ZigVersion, Box<dyn std::error::Error>> {
+    version
+        .parse()
+        .map_err(|e| format!("{} in {}", e, path.display()).into())
+}
+
+// Check a single directory for a version file
+fn find_version_in_dir(dir: &Path) -> Result<Option<ActiveVersion>, Box<dyn std::error::Error>> {
+    let zig_version_file = dir.join(ZIG_VERSION_FILE);
+    if file_exists(&zig_version_file)
+        && let Some(version) = read_zig_version_file(&zig_version_file)?
+    {
+        return Ok(Some(ActiveVersion {
+            version: parse_pinned_version(&version, &zig_version_file)?,
+            source: VersionSource::ZigVersionFile(zig_version_file),
+        }));
+    }
+
+    let tool_versions = dir.join(TOOL_VERSIONS_FILE);
+    if file_exists(&tool_versions)
+        && let Some(version) = read_tool_versions(&tool_versions)?
+    {
+        return Ok(Some(ActiveVersion {
+            version: parse_pinned_version(&version, &tool_versions)?,
+            source: VersionSource::ToolVersions(tool_versions),
+        }));
+    }
+
+    let project_file = dir.join(PROJECT_FILE);
+    if file_exists(&project_file)
+        && let Some(version) = read_project_file(&project_file)?
+    {
+        return Ok(Some(ActiveVersion {
+            version: parse_pinned_version(&version, &project_file)?,
+            source: VersionSource::ProjectFile(project_file),
+        }));
+    }
+
+    Ok(None)
+}
+
+// Walk up from a directory looking for a project version file
+pub fn find_project_version(
+    start_dir: &Path,
+) -> Result<Option<ActiveVersion>, Box<dyn std::error::Error>> {
+    for dir in start_dir.ancestors() {
+        if let Some(active) = find_version_in_dir(dir)? {
+            return Ok(Some(active));
+        }
+    }
+
+    Ok(None)
+}
+
+// Read the global default version from ~/.zpm/current
+pub fn get_default_version(home_dir: &str) -> Option<ZigVersion> {
+    let current_file = get_current_file(home_dir);
+    if !file_exists(&current_file) {
+        return None;
+    }
+
+    fs::read_to_string(current_file)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+}
+
+// Whether a concrete version is the global default, directly or through `master`
+pub fn is_default_version(home_dir: &str, version: &ZigVersion) -> bool {
+    match get_default_version(home_dir) {
+        Some(default) if default.is_master() => read_master_alias(home_dir).as_ref() == Some(version),
+        Some(default) => &default == version,
+        None => false,
+    }
+}
+
+// Resolve the active version for a directory: the environment override first,
+// then project files, then the global default
+pub fn resolve_version(
+    home_dir: &str,
+    start_dir: &Path,
+) -> Result<Option<ActiveVersion>, Box<dyn std::error::Error>> {
+    if let Some(version) = std::env::var(VERSION_ENV)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+    {
+        return Ok(Some(ActiveVersion {
+            version: version
+                .parse()
+                .map_err(|e| format!("{} in {}", e, VERSION_ENV))?,
+            source: VersionSource::Environment,
+        }));
+    }
+
+    if let Some(active) = find_project_version(start_dir)? {
+        return Ok(Some(active));
+    }
+
+    Ok(get_default_version(home_dir).map(|version| ActiveVersion {
+        version,
+        source: VersionSource::Default,
+    }))
+}
+
+// Resolve the active version for the current working directory
+pub fn resolve_active_version(
+    home_dir: &str,
+) -> Result<Option<ActiveVersion>, Box<dyn std::error::Error>> {
+    let cwd = std::env::current_dir()?;
+    resolve_version(home_dir, &cwd)
+}
+
+// Walk up from a directory looking for build.zig.zon
+pub fn find_build_zon(start_dir: &Path) -> Option<PathBuf> {
+    start_dir
+        .ancestors()
+        .map(|dir| dir.join(BUILD_ZON_FILE))
+        .find(|path| file_exists(path))
+}
+
+// Read `minimum_zig_version` from a build.zig.zon manifest
+pub fn read_minimum_zig_version(path: &Path) -> Result<Option<ZigVersion>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let manifest = crate::zon::parse(&contents)
+        .map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))?;
+
+    manifest
+        .get("minimum_zig_version")
+        .and_then(|v| v.as_str())
+        .map(|v| parse_pinned_version(v.trim(), path))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory for one test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zpm-project-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn v(s: &str) -> ZigVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn walks_up_to_the_nearest_version_file() {
+        let root = temp_dir("walk");
+        let nested = root.join("app/src/deep");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(ZIG_VERSION_FILE), "0.12.0\n").unwrap();
+
+        let active = find_project_version(&nested).unwrap().unwrap();
+        assert_eq!(active.version, v("0.12.0"));
+        assert_eq!(active.source, VersionSource::ZigVersionFile(root.join(ZIG_VERSION_FILE)));
+
+        // A nearer file of any kind wins over one further up
+        fs::write(root.join("app").join(PROJECT_FILE), r#"{ "zig": "0.13.0" }"#).unwrap();
+        let active = find_project_version(&nested).unwrap().unwrap();
+        assert_eq!(active.version, v("0.13.0"));
+        assert_eq!(active.source, VersionSource::ProjectFile(root.join("app").join(PROJECT_FILE)));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn prefers_zig_version_then_tool_versions_then_project_file() {
+        let dir = temp_dir("precedence");
+        fs::write(dir.join(PROJECT_FILE), r#"{ "zig": "0.11.0" }"#).unwrap();
+        assert_eq!(find_project_version(&dir).unwrap().unwrap().version, v("0.11.0"));
+
+        fs::write(dir.join(TOOL_VERSIONS_FILE), "zig 0.12.0\n").unwrap();
+        let active = find_project_version(&dir).unwrap().unwrap();
+        assert_eq!(active.version, v("0.12.0"));
+        assert_eq!(active.source, VersionSource::ToolVersions(dir.join(TOOL_VERSIONS_FILE)));
+
+        fs::write(dir.join(ZIG_VERSION_FILE), "# pinned\n\n0.13.0\n").unwrap();
+        let active = find_project_version(&dir).unwrap().unwrap();
+        assert_eq!(active.version, v("0.13.0"));
+        assert_eq!(active.source, VersionSource::ZigVersionFile(dir.join(ZIG_VERSION_FILE)));
+
+        // A file without a version falls through to the next kind
+        fs::write(dir.join(ZIG_VERSION_FILE), "# nothing yet\n").unwrap();
+        assert_eq!(find_project_version(&dir).unwrap().unwrap().version, v("0.12.0"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reads_the_zig_entry_of_tool_versions() {
+        let dir = temp_dir("tool-versions");
+        let path = dir.join(TOOL_VERSIONS_FILE);
+        let read = |contents: &str| {
+            fs::write(&path, contents).unwrap();
+            read_tool_versions(&path).unwrap()
+        };
+
+        assert_eq!(read("nodejs 20.1.0\nzig 0.13.0\npython 3.12.0\n").as_deref(), Some("0.13.0"));
+        assert_eq!(read("# zig 0.10.0\nzig 0.13.0 # pinned for CI\n").as_deref(), Some("0.13.0"));
+        assert_eq!(read("zig 0.14.0-dev.1+abcdef123 0.13.0 system\n").as_deref(), Some("0.14.0-dev.1+abcdef123"));
+        assert_eq!(read("  zig\t0.13.0  \n").as_deref(), Some("0.13.0"));
+        assert_eq!(read("zigmod 1.0.0\nnodejs 20.1.0\n"), None);
+        assert_eq!(read("zig\n"), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn names_the_file_with_an_invalid_version() {
+        let dir = temp_dir("invalid");
+        fs::write(dir.join(ZIG_VERSION_FILE), "../x\n").unwrap();
+        let error = find_project_version(&dir).unwrap_err().to_string();
+        assert!(error.contains(ZIG_VERSION_FILE), "{}", error);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
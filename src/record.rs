@@ -0,0 +1,36 @@
+// Per-install metadata kept next to each installed version
+use crate::utils::{get_version_dir, write_atomic};
+use crate::version::ZigVersion;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const RECORD_FILE: &str = ".zpm.json";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct InstallRecord {
+    // The mirror or origin URL the archive was downloaded from
+    pub source: Option<String>,
+    // SHA-256 of the archive in the cache it was installed from
+    pub archive_sha256: Option<String>,
+    // The ZLS release installed for this version, if any
+    pub zls_version: Option<String>,
+    pub zls_archive_sha256: Option<String>,
+}
+
+fn get_record_file(home_dir: &str, version: &ZigVersion) -> PathBuf {
+    get_version_dir(home_dir, version).join(RECORD_FILE)
+}
+
+pub fn read_install_record(home_dir: &str, version: &ZigVersion) -> Option<InstallRecord> {
+    let contents = std::fs::read_to_string(get_record_file(home_dir, version)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+pub fn write_install_record(
+    home_dir: &str,
+    version: &ZigVersion,
+    record: &InstallRecord,
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_atomic(&get_record_file(home_dir, version), serde_json::to_string_pretty(record)?)?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+// Multicall shims: zpm installed as `zig` or `zls` dispatches to the active version
+use crate::config::load_config;
+use crate::http::HttpClient;
+use crate::lock::lock_zpm;
+use crate::project::resolve_active_version;
+use crate::utils::{
+    file_exists, find_zls_binary, get_local_bin_dir, get_zig_binary, get_zig_symlink,
+    get_zls_dir, get_zls_symlink, replace_symlink,
+};
+use crate::version::ZigVersion;
+use crate::version_req::VersionReq;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+
+// Set to 1 to install missing versions on first use
+pub const AUTO_INSTALL_ENV: &str = "ZPM_AUTO_INSTALL";
+
+// The tools zpm can stand in for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shim {
+    Zig,
+    Zls,
+}
+
+impl Shim {
+    pub fn name(self) -> &'static str {
+        match self {
+            Shim::Zig => "zig",
+            Shim::Zls => "zls",
+        }
+    }
+}
+
+// Detect shim mode from argv[0]
+pub fn shim_from_argv0(argv0: &OsStr) -> Option<Shim> {
+    match Path::new(argv0).file_name()?.to_str()? {
+        "zig" => Some(Shim::Zig),
+        "zls" => Some(Shim::Zls),
+        _ => None,
+    }
+}
+
+// Check whether a path is a shim pointing at this zpm executable
+pub fn is_shim(path: &Path) -> bool {
+    let Ok(exe) = std::env::current_exe().and_then(fs::canonicalize) else {
+        return false;
+    };
+    fs::canonicalize(path).map(|p| p == exe).unwrap_or(false)
+}
+
+fn auto_install_enabled() -> bool {
+    matches!(
+        std::env::var(AUTO_INSTALL_ENV).as_deref(),
+        Ok("1") | Ok("true") | Ok("yes")
+    )
+}
+
+// Install a missing version on demand; only this path starts the async runtime
+fn auto_install(home_dir: &str, version: &ZigVersion) -> Result<(), Box<dyn std::error::Error>> {
+    eprintln!("zpm: Zig version {} is not installed, installing...", version);
+    let client = HttpClient::new(&load_config(home_dir)?)?;
+    let _lock = lock_zpm(home_dir)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(crate::commands::install(
+        &client,
+        home_dir,
+        Some(&VersionReq::Exact(version.clone())),
+        // The wrapped command's stdout may be parsed, e.g. `zig env`
+        &crate::commands::InstallOptions { progress_to_stderr: true, ..Default::default() },
+    ))?;
+    Ok(())
+}
+
+// Resolve the active version and replace this process with the real binary
+pub fn run_shim(
+    shim: Shim,
+    home_dir: &str,
+    args: Vec<OsString>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let active = resolve_active_version(home_dir)?.ok_or(
+        "No Zig version selected. Add a .zig-version file or run `zpm use <version>`.",
+    )?;
+    let version = active.version;
+
+    let zig_binary = get_zig_binary(home_dir, &version);
+    if !file_exists(&zig_binary) {
+        if !auto_install_enabled() {
+            return Err(format!(
+                "Zig version {} is not installed. Run `zpm install {}` or set {}=1.",
+                version, version, AUTO_INSTALL_ENV
+            )
+            .into());
+        }
+        auto_install(home_dir, &version)?;
+    }
+
+    let binary = match shim {
+        Shim::Zig => zig_binary,
+        Shim::Zls => find_zls_binary(&get_zls_dir(home_dir, &version)).map_err(|_| {
+            format!(
+                "ZLS is not installed for Zig version {}. Run `zpm install-zls`.",
+                version
+            )
+        })?,
+    };
+
+    // exec only returns on failure
+    let err = Command::new(&binary).args(args).exec();
+    Err(format!("Failed to execute {}: {}", binary.display(), err).into())
+}
+
+// Point ~/.local/bin/zig and ~/.local/bin/zls at the zpm executable
+pub fn install_shims(home_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let exe = fs::canonicalize(std::env::current_exe()?)?;
+    fs::create_dir_all(get_local_bin_dir(home_dir))?;
+
+    for link in [get_zig_symlink(home_dir), get_zls_symlink(home_dir)] {
+        replace_symlink(&exe, &link)?;
+        println!("Installed shim {}", link.display());
+    }
+
+    Ok(())
+}
+
+// Remove the shims, leaving any regular symlinks in place
+pub fn remove_shims(home_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    for link in [get_zig_symlink(home_dir), get_zls_symlink(home_dir)] {
+        if is_shim(&link) {
+            fs::remove_file(&link)?;
+            println!("Removed shim {}", link.display());
+        }
+    }
+
+    Ok(())
+}
@@ -1,15 +1,90 @@
+use crate::version::ZigVersion;
 use std::path::{Path, PathBuf};
 
 const ZPM_DIR: &str = ".zpm";
 const VERSIONS_DIR: &str = "versions";
 const CACHE_DIR: &str = "cache";
 const CURRENT_FILE: &str = "current";
+const CONFIG_FILE: &str = "config.json";
+const MASTER_ALIAS: &str = "master";
+const STAGING_PREFIX: &str = ".staging-";
 
 // File system utilities
 pub fn file_exists(path: &Path) -> bool {
     path.exists()
 }
 
+// A sibling path to build a replacement in before renaming it over `path`
+fn temp_sibling(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".tmp-{}", std::process::id()));
+    path.with_file_name(name)
+}
+
+// Replace a file's contents in one step, so readers never see a partial write
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let temp = temp_sibling(path);
+    let result = std::fs::write(&temp, contents).and_then(|()| std::fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+// Point a symlink at a new target in one step, replacing whatever was there
+pub fn replace_symlink(target: impl AsRef<Path>, link: &Path) -> std::io::Result<()> {
+    let temp = temp_sibling(link);
+    let _ = std::fs::remove_file(&temp);
+    let result = std::os::unix::fs::symlink(target, &temp).and_then(|()| std::fs::rename(&temp, link));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+// Remove a symlink that points into `dir`, leaving anything else alone
+pub fn remove_symlink_into(link: &Path, dir: &Path) -> std::io::Result<()> {
+    match std::fs::read_link(link) {
+        Ok(target) if target.starts_with(dir) => std::fs::remove_file(link),
+        _ => Ok(()),
+    }
+}
+
+// Sends this process's stdout to stderr until dropped, for progress that must
+// not mix with the stdout of a command zpm runs afterwards
+pub struct StdoutToStderr {
+    saved: libc::c_int,
+}
+
+pub fn redirect_stdout_to_stderr() -> std::io::Result<StdoutToStderr> {
+    std::io::Write::flush(&mut std::io::stdout())?;
+    // SAFETY: only duplicates and closes descriptors this function owns
+    unsafe {
+        let saved = libc::dup(libc::STDOUT_FILENO);
+        if saved < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+            let error = std::io::Error::last_os_error();
+            libc::close(saved);
+            return Err(error);
+        }
+        Ok(StdoutToStderr { saved })
+    }
+}
+
+impl Drop for StdoutToStderr {
+    fn drop(&mut self) {
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+        // SAFETY: restores the descriptor saved by redirect_stdout_to_stderr
+        unsafe {
+            libc::dup2(self.saved, libc::STDOUT_FILENO);
+            libc::close(self.saved);
+        }
+    }
+}
+
 // Path management functions
 pub fn get_zpm_dir(home_dir: &str) -> PathBuf {
     Path::new(home_dir).join(ZPM_DIR)
@@ -27,14 +102,121 @@ pub fn get_current_file(home_dir: &str) -> PathBuf {
     get_zpm_dir(home_dir).join(CURRENT_FILE)
 }
 
-pub fn get_version_dir(home_dir: &str, version: &str) -> PathBuf {
-    get_versions_dir(home_dir).join(version)
+pub fn get_config_file(home_dir: &str) -> PathBuf {
+    get_zpm_dir(home_dir).join(CONFIG_FILE)
+}
+
+pub fn get_version_dir(home_dir: &str, version: &ZigVersion) -> PathBuf {
+    get_versions_dir(home_dir).join(version.to_string())
+}
+
+// Where a version is unpacked before it is moved into place. The leading dot
+// keeps it from being listed as an installed version.
+pub fn get_staging_dir(home_dir: &str, version: &ZigVersion) -> PathBuf {
+    get_versions_dir(home_dir).join(format!("{}{}", STAGING_PREFIX, version))
+}
+
+// Where ZLS is built from source for a version; removed like any other staging directory
+pub fn get_zls_build_dir(home_dir: &str, version: &ZigVersion) -> PathBuf {
+    get_versions_dir(home_dir).join(format!("{}zls-{}", STAGING_PREFIX, version))
+}
+
+// Remove staging directories left behind by installs that never finished
+pub fn clean_stale_staging(home_dir: &str) -> std::io::Result<()> {
+    let Ok(dir) = std::fs::read_dir(get_versions_dir(home_dir)) else {
+        return Ok(());
+    };
+    for entry in dir.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(STAGING_PREFIX) {
+            println!("Removing unfinished install {}", entry.path().display());
+            std::fs::remove_dir_all(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+// The `master` alias is a symlink in the versions directory to the newest nightly
+pub fn get_master_alias(home_dir: &str) -> PathBuf {
+    get_versions_dir(home_dir).join(MASTER_ALIAS)
+}
+
+// Read which concrete nightly the master alias points to
+pub fn read_master_alias(home_dir: &str) -> Option<ZigVersion> {
+    std::fs::read_link(get_master_alias(home_dir))
+        .ok()
+        .and_then(|target| target.file_name()?.to_str()?.parse().ok())
+}
+
+// Older zpm versions installed master into a real `versions/master` directory.
+// Move it to the dev version its zig reports, or remove it if that is unknown
+// or already installed, so the alias can take its place.
+fn migrate_legacy_master(home_dir: &str) -> std::io::Result<()> {
+    let legacy = get_master_alias(home_dir);
+    if !std::fs::symlink_metadata(&legacy).is_ok_and(|m| m.is_dir()) {
+        return Ok(());
+    }
+
+    let reported = std::process::Command::new(legacy.join("zig"))
+        .arg("version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse::<ZigVersion>().ok())
+        .filter(|version| version.is_dev());
+    match reported {
+        Some(version) if !get_version_dir(home_dir, &version).exists() => {
+            println!("Moving the old master install to {}", version);
+            std::fs::rename(&legacy, get_version_dir(home_dir, &version))
+        }
+        _ => {
+            println!("Removing the old master install at {}", legacy.display());
+            std::fs::remove_dir_all(&legacy)
+        }
+    }
+}
+
+// Point the master alias at a concrete nightly version directory
+pub fn set_master_alias(home_dir: &str, version: &ZigVersion) -> std::io::Result<()> {
+    migrate_legacy_master(home_dir)?;
+    replace_symlink(version.to_string(), &get_master_alias(home_dir))
 }
 
-pub fn get_zig_binary(home_dir: &str, version: &str) -> PathBuf {
+// Remove the master alias if it exists
+pub fn remove_master_alias(home_dir: &str) -> std::io::Result<()> {
+    let alias = get_master_alias(home_dir);
+    if std::fs::symlink_metadata(&alias).is_ok() {
+        std::fs::remove_file(&alias)?;
+    }
+    Ok(())
+}
+
+// List installed versions, skipping directories that are not version names and
+// a legacy `master` directory
+pub fn get_installed_versions(home_dir: &str) -> Vec<ZigVersion> {
+    let mut versions: Vec<ZigVersion> = Vec::new();
+    
+    if let Ok(dir) = std::fs::read_dir(get_versions_dir(home_dir)) {
+        for entry in dir.flatten() {
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false)
+                && let Some(version) = entry.file_name().to_str().and_then(|n| n.parse::<ZigVersion>().ok())
+                && !version.is_master()
+            {
+                versions.push(version);
+            }
+        }
+    }
+    
+    versions
+}
+
+pub fn get_zig_binary(home_dir: &str, version: &ZigVersion) -> PathBuf {
     get_version_dir(home_dir, version).join("zig")
 }
 
+pub fn get_zls_dir(home_dir: &str, version: &ZigVersion) -> PathBuf {
+    get_version_dir(home_dir, version).join("zls")
+}
+
 pub fn get_local_bin_dir(home_dir: &str) -> PathBuf {
     Path::new(home_dir).join(".local").join("bin")
 }
@@ -43,6 +225,32 @@ pub fn get_zig_symlink(home_dir: &str) -> PathBuf {
     get_local_bin_dir(home_dir).join("zig")
 }
 
+pub fn get_zls_symlink(home_dir: &str) -> PathBuf {
+    get_local_bin_dir(home_dir).join("zls")
+}
+
+// Find the ZLS binary in an extracted ZLS directory
+pub fn find_zls_binary(zls_dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    // Look for the zls binary in the extracted directory
+    let mut queue = vec![zls_dir.to_path_buf()];
+    
+    while let Some(dir) = queue.pop() {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                
+                if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                    queue.push(path);
+                } else if entry.file_name() == "zls" {
+                    return Ok(path);
+                }
+            }
+        }
+    }
+    
+    Err("Failed to find ZLS binary in extracted archive".into())
+}
+
 // Platform detection
 pub fn get_platform_string() -> Result<String, Box<dyn std::error::Error>> {
     let arch = match std::env::consts::ARCH {
@@ -61,33 +269,3 @@ pub fn get_platform_string() -> Result<String, Box<dyn std::error::Error>> {
     
     Ok(format!("{}-{}", arch, os))
 }
-
-// Version comparison
-pub fn version_compare(a: &str, b: &str) -> std::cmp::Ordering {
-    let a_is_master = a == "master";
-    let b_is_master = b == "master";
-    
-    if a_is_master && b_is_master {
-        return std::cmp::Ordering::Equal;
-    }
-    if a_is_master {
-        return std::cmp::Ordering::Greater;
-    }
-    if b_is_master {
-        return std::cmp::Ordering::Less;
-    }
-    
-    let a_parts: Vec<&str> = a.split('.').collect();
-    let b_parts: Vec<&str> = b.split('.').collect();
-    
-    for (a_part, b_part) in a_parts.iter().zip(b_parts.iter()) {
-        let a_num = a_part.parse::<u32>().unwrap_or(0);
-        let b_num = b_part.parse::<u32>().unwrap_or(0);
-        
-        if a_num != b_num {
-            return b_num.cmp(&a_num);
-        }
-    }
-    
-    b_parts.len().cmp(&a_parts.len())
-}
@@ -0,0 +1,308 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+// A pre-release identifier, e.g. `dev` or `1234` in `0.14.0-dev.1234`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PreRelease {
+    Numeric(u64),
+    Alpha(String),
+}
+
+impl Ord for PreRelease {
+    // Numeric identifiers sort numerically and before alphanumeric ones (semver rules)
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (PreRelease::Numeric(a), PreRelease::Numeric(b)) => a.cmp(b),
+            (PreRelease::Numeric(_), PreRelease::Alpha(_)) => Ordering::Less,
+            (PreRelease::Alpha(_), PreRelease::Numeric(_)) => Ordering::Greater,
+            (PreRelease::Alpha(a), PreRelease::Alpha(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for PreRelease {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for PreRelease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreRelease::Numeric(n) => write!(f, "{}", n),
+            PreRelease::Alpha(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+// A Zig version: a release (0.13.0), a dev build (0.14.0-dev.1234+abcdef123),
+// a vendor build (0.12.0-mach), or the `master` alias
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ZigVersion {
+    Master,
+    Version {
+        major: u64,
+        minor: u64,
+        patch: u64,
+        pre: Vec<PreRelease>,
+        build: Option<String>,
+    },
+}
+
+impl ZigVersion {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        ZigVersion::Version {
+            major,
+            minor,
+            patch,
+            pre: Vec::new(),
+            build: None,
+        }
+    }
+
+    pub fn is_master(&self) -> bool {
+        matches!(self, ZigVersion::Master)
+    }
+
+    // A tagged release with no pre-release suffix
+    pub fn is_stable(&self) -> bool {
+        matches!(self, ZigVersion::Version { pre, .. } if pre.is_empty())
+    }
+
+    // A nightly build such as 0.14.0-dev.1234+abcdef123
+    pub fn is_dev(&self) -> bool {
+        self.dev_number().is_some()
+            && self
+                .commit()
+                .is_some_and(|c| c.chars().all(|c| c.is_ascii_hexdigit()))
+    }
+
+    // The (major, minor, patch) triple, if this is not `master`
+    pub fn triple(&self) -> Option<(u64, u64, u64)> {
+        match self {
+            ZigVersion::Master => None,
+            ZigVersion::Version {
+                major,
+                minor,
+                patch,
+                ..
+            } => Some((*major, *minor, *patch)),
+        }
+    }
+
+    // The dev build number: commits since the release tag
+    pub fn dev_number(&self) -> Option<u64> {
+        match self {
+            ZigVersion::Version { pre, .. } => match pre.as_slice() {
+                [PreRelease::Alpha(tag), PreRelease::Numeric(n)] if tag == "dev" => Some(*n),
+                _ => None,
+            },
+            ZigVersion::Master => None,
+        }
+    }
+
+    // The commit a dev build was made from
+    pub fn commit(&self) -> Option<&str> {
+        match self {
+            ZigVersion::Version { build, .. } => build.as_deref(),
+            ZigVersion::Master => None,
+        }
+    }
+
+    // Build a dev version from a release triple, dev number and commit
+    pub fn dev(triple: (u64, u64, u64), number: u64, commit: &str) -> Self {
+        ZigVersion::Version {
+            major: triple.0,
+            minor: triple.1,
+            patch: triple.2,
+            pre: vec![
+                PreRelease::Alpha("dev".to_string()),
+                PreRelease::Numeric(number),
+            ],
+            build: Some(commit.to_string()),
+        }
+    }
+}
+
+impl Ord for ZigVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (ZigVersion::Master, ZigVersion::Master) => Ordering::Equal,
+            (ZigVersion::Master, _) => Ordering::Greater,
+            (_, ZigVersion::Master) => Ordering::Less,
+            (
+                ZigVersion::Version {
+                    major: a_major,
+                    minor: a_minor,
+                    patch: a_patch,
+                    pre: a_pre,
+                    build: a_build,
+                },
+                ZigVersion::Version {
+                    major: b_major,
+                    minor: b_minor,
+                    patch: b_patch,
+                    pre: b_pre,
+                    build: b_build,
+                },
+            ) => (a_major, a_minor, a_patch)
+                .cmp(&(b_major, b_minor, b_patch))
+                // A release sorts after all of its pre-releases
+                .then_with(|| match (a_pre.is_empty(), b_pre.is_empty()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    (false, false) => a_pre.cmp(b_pre),
+                })
+                // Build metadata carries no precedence; compare it only to stay consistent with Eq
+                .then_with(|| a_build.cmp(b_build)),
+        }
+    }
+}
+
+impl PartialOrd for ZigVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for ZigVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZigVersion::Master => write!(f, "master"),
+            ZigVersion::Version {
+                major,
+                minor,
+                patch,
+                pre,
+                build,
+            } => {
+                write!(f, "{}.{}.{}", major, minor, patch)?;
+                if !pre.is_empty() {
+                    let pre: Vec<String> = pre.iter().map(|p| p.to_string()).collect();
+                    write!(f, "-{}", pre.join("."))?;
+                }
+                if let Some(build) = build {
+                    write!(f, "+{}", build)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn parse_number(part: &str, input: &str) -> Result<u64, String> {
+    // Leading zeros are not allowed, as in semver
+    if part.is_empty() || (part.len() > 1 && part.starts_with('0')) {
+        return Err(format!("Invalid version: {}", input));
+    }
+    part.parse()
+        .map_err(|_| format!("Invalid version: {}", input))
+}
+
+impl FromStr for ZigVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "master" {
+            return Ok(ZigVersion::Master);
+        }
+
+        // Only these characters can appear in a version, which also keeps a
+        // version from ever being a path outside the versions directory
+        if s.is_empty()
+            || !s
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+        {
+            return Err(format!("Invalid version: {}", s));
+        }
+
+        let (rest, build) = match s.split_once('+') {
+            Some((rest, build)) => (rest, Some(build)),
+            None => (s, None),
+        };
+        let (core, pre) = match rest.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (rest, None),
+        };
+
+        let parts: Vec<&str> = core.split('.').collect();
+        let [major, minor, patch] = parts.as_slice() else {
+            return Err(format!("Invalid version: {}", s));
+        };
+
+        let pre = match pre {
+            Some(pre) => pre
+                .split('.')
+                .map(|id| {
+                    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                        Err(format!("Invalid version: {}", s))
+                    } else if id.chars().all(|c| c.is_ascii_digit()) {
+                        parse_number(id, s).map(PreRelease::Numeric)
+                    } else {
+                        Ok(PreRelease::Alpha(id.to_string()))
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        let build = match build {
+            Some(b)
+                if b.is_empty()
+                    || !b.split('.').all(|id| {
+                        !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                    }) =>
+            {
+                return Err(format!("Invalid version: {}", s));
+            }
+            b => b.map(|b| b.to_string()),
+        };
+
+        Ok(ZigVersion::Version {
+            major: parse_number(major, s)?,
+            minor: parse_number(minor, s)?,
+            patch: parse_number(patch, s)?,
+            pre,
+            build,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> ZigVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn dev_builds_sort_before_their_release() {
+        assert!(v("0.14.0-dev.1234+abcdef123") < v("0.14.0"));
+        assert!(v("0.13.0") < v("0.14.0-dev.1+abcdef123"));
+        assert!(v("0.14.0") < ZigVersion::Master);
+    }
+
+    #[test]
+    fn dev_numbers_sort_numerically() {
+        assert!(v("0.14.0-dev.99+abcdef123") < v("0.14.0-dev.100+abcdef123"));
+        assert_eq!(v("0.14.0-dev.100+abcdef123").dev_number(), Some(100));
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        for s in ["0.13.0", "0.14.0-dev.1234+abcdef123", "0.12.0-mach", "master"] {
+            assert_eq!(v(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn rejects_path_like_input() {
+        for s in ["../x", "..", "0.13.0/../x", "0.13.0/", "/0.13.0", "", "0.13", "01.2.3"] {
+            assert!(s.parse::<ZigVersion>().is_err(), "{} should not parse", s);
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use crate::models::ReleaseIndex;
+use crate::utils::{get_installed_versions, read_master_alias};
+use crate::version::ZigVersion;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Op::Eq => "=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+// A single bound such as `>=0.12.0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparator {
+    pub op: Op,
+    pub version: ZigVersion,
+}
+
+impl Comparator {
+    fn matches(&self, version: &ZigVersion) -> bool {
+        match self.op {
+            Op::Eq => version == &self.version,
+            Op::Gt => version > &self.version,
+            Op::Ge => version >= &self.version,
+            Op::Lt => version < &self.version,
+            Op::Le => version <= &self.version,
+        }
+    }
+}
+
+// What a user asked for: a keyword, an exact version, or a range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionReq {
+    LatestStable,
+    Nightly,
+    Exact(ZigVersion),
+    Range(Vec<Comparator>),
+}
+
+impl VersionReq {
+    // Check a concrete version against the requirement
+    pub fn matches(&self, version: &ZigVersion) -> bool {
+        match self {
+            VersionReq::LatestStable => version.is_stable(),
+            VersionReq::Nightly => version.is_master() || version.is_dev(),
+            VersionReq::Exact(exact) => version == exact,
+            VersionReq::Range(comparators) => {
+                if version.is_master() {
+                    return false;
+                }
+                // As in semver, pre-releases only match a range that names a
+                // pre-release of the same release
+                if !version.is_stable()
+                    && !comparators
+                        .iter()
+                        .any(|c| !c.version.is_stable() && c.version.triple() == version.triple())
+                {
+                    return false;
+                }
+                comparators.iter().all(|c| c.matches(version))
+            }
+        }
+    }
+
+    // Resolve against the release index. Nightly requirements resolve to `master`.
+    pub fn resolve_index(&self, releases: &ReleaseIndex) -> Result<ZigVersion, Box<dyn std::error::Error>> {
+        let resolved = match self {
+            VersionReq::Nightly => Some(ZigVersion::Master),
+            VersionReq::Exact(exact) => Some(exact.clone()),
+            _ => {
+                let master_version = releases.master_version();
+                releases
+                    .releases()
+                    .map(|(v, _)| v)
+                    .chain(master_version.clone())
+                    .filter(|v| self.matches(v))
+                    .max()
+                    .map(|v| if Some(&v) == master_version.as_ref() { ZigVersion::Master } else { v })
+            }
+        };
+
+        resolved.ok_or_else(|| format!("No Zig release matches {}", self).into())
+    }
+
+    // Resolve against the installed versions. Nightly requirements resolve to the
+    // nightly the master alias points at.
+    pub fn resolve_installed(&self, home_dir: &str) -> Option<ZigVersion> {
+        let master_alias = read_master_alias(home_dir);
+        match self {
+            VersionReq::Nightly | VersionReq::Exact(ZigVersion::Master) => master_alias,
+            _ => get_installed_versions(home_dir)
+                .into_iter()
+                .filter(|v| self.matches(v))
+                .max(),
+        }
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionReq::LatestStable => write!(f, "latest-stable"),
+            VersionReq::Nightly => write!(f, "nightly"),
+            VersionReq::Exact(version) => write!(f, "{}", version),
+            VersionReq::Range(comparators) => {
+                let parts: Vec<String> = comparators
+                    .iter()
+                    .map(|c| format!("{}{}", c.op, c.version))
+                    .collect();
+                write!(f, "{}", parts.join(" "))
+            }
+        }
+    }
+}
+
+// A possibly partial version: `0`, `0.13`, `0.13.1`, with `x`/`*` wildcards
+struct Partial {
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+}
+
+impl Partial {
+    fn lower(&self) -> ZigVersion {
+        ZigVersion::new(self.major, self.minor.unwrap_or(0), self.patch.unwrap_or(0))
+    }
+
+    // The first version past everything the partial covers
+    fn upper(&self) -> ZigVersion {
+        match self.minor {
+            None => ZigVersion::new(self.major + 1, 0, 0),
+            Some(minor) => ZigVersion::new(self.major, minor + 1, 0),
+        }
+    }
+}
+
+fn parse_partial(s: &str, input: &str) -> Result<Partial, String> {
+    let invalid = || format!("Invalid version requirement: {}", input);
+    let parse = |p: &str| -> Result<Option<u64>, String> {
+        match p {
+            "x" | "X" | "*" => Ok(None),
+            p if !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()) => {
+                p.parse().map(Some).map_err(|_| invalid())
+            }
+            _ => Err(invalid()),
+        }
+    };
+
+    let parts: Vec<&str> = s.split('.').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        return Err(invalid());
+    }
+    let major = parse(parts[0])?.ok_or_else(invalid)?;
+    let minor = parts.get(1).map(|p| parse(p)).transpose()?.flatten();
+    let patch = parts.get(2).map(|p| parse(p)).transpose()?.flatten();
+    if minor.is_none() && patch.is_some() {
+        return Err(invalid());
+    }
+
+    Ok(Partial { major, minor, patch })
+}
+
+// Parse one term of a range, e.g. `>=0.12`, `~0.12.1` or `0.13`
+fn parse_term(term: &str, input: &str) -> Result<Vec<Comparator>, String> {
+    let (op, rest) = [">=", "<=", ">", "<", "=", "~", "^"]
+        .iter()
+        .find_map(|op| term.strip_prefix(op).map(|rest| (*op, rest.trim())))
+        .unwrap_or(("", term));
+
+    // A full version (possibly a dev build) is used as-is
+    if let Ok(version) = rest.parse::<ZigVersion>()
+        && !version.is_master()
+    {
+        let op = match op {
+            ">=" => Op::Ge,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            "<" => Op::Lt,
+            "=" | "" => Op::Eq,
+            // ~x.y.z and ^0.y.z both allow patch updates within the minor series
+            _ => {
+                let (major, minor, _) = version.triple().expect("not master");
+                let upper = if op == "^" && major > 0 {
+                    ZigVersion::new(major + 1, 0, 0)
+                } else {
+                    ZigVersion::new(major, minor + 1, 0)
+                };
+                return Ok(vec![
+                    Comparator { op: Op::Ge, version },
+                    Comparator { op: Op::Lt, version: upper },
+                ]);
+            }
+        };
+        return Ok(vec![Comparator { op, version }]);
+    }
+
+    let partial = parse_partial(rest, input)?;
+    let comparators = match op {
+        ">=" => vec![Comparator { op: Op::Ge, version: partial.lower() }],
+        ">" => vec![Comparator { op: Op::Ge, version: partial.upper() }],
+        "<" => vec![Comparator { op: Op::Lt, version: partial.lower() }],
+        "<=" => vec![Comparator { op: Op::Lt, version: partial.upper() }],
+        "^" if partial.major > 0 => vec![
+            Comparator { op: Op::Ge, version: partial.lower() },
+            Comparator { op: Op::Lt, version: ZigVersion::new(partial.major + 1, 0, 0) },
+        ],
+        _ => vec![
+            Comparator { op: Op::Ge, version: partial.lower() },
+            Comparator { op: Op::Lt, version: partial.upper() },
+        ],
+    };
+    Ok(comparators)
+}
+
+impl FromStr for VersionReq {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "latest-stable" | "stable" | "latest" => return Ok(VersionReq::LatestStable),
+            "nightly" | "master" => return Ok(VersionReq::Nightly),
+            "" => return Err("Empty version requirement".to_string()),
+            _ => {}
+        }
+
+        if let Ok(version) = s.parse::<ZigVersion>() {
+            return Ok(VersionReq::Exact(version));
+        }
+
+        // Terms are separated by whitespace or commas; an operator may be
+        // separated from its version by a space (`>= 0.12`)
+        let mut terms: Vec<String> = Vec::new();
+        let mut pending_op: Option<String> = None;
+        for token in s.split([' ', ',']).filter(|t| !t.is_empty()) {
+            if token.chars().all(|c| matches!(c, '<' | '>' | '=' | '~' | '^')) {
+                pending_op = Some(token.to_string());
+            } else {
+                terms.push(format!("{}{}", pending_op.take().unwrap_or_default(), token));
+            }
+        }
+        if pending_op.is_some() || terms.is_empty() {
+            return Err(format!("Invalid version requirement: {}", s));
+        }
+
+        let mut comparators = Vec::new();
+        for term in &terms {
+            comparators.extend(parse_term(term, s)?);
+        }
+        Ok(VersionReq::Range(comparators))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(s: &str) -> VersionReq {
+        s.parse().unwrap()
+    }
+
+    fn v(s: &str) -> ZigVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn partial_version_covers_its_minor_series() {
+        let r = req("0.13");
+        assert_eq!(r.to_string(), ">=0.13.0 <0.14.0");
+        assert!(r.matches(&v("0.13.0")));
+        assert!(r.matches(&v("0.13.1")));
+        assert!(!r.matches(&v("0.14.0")));
+        assert!(!r.matches(&v("0.14.0-dev.1+abcdef123")));
+    }
+
+    #[test]
+    fn tilde_allows_patch_updates() {
+        let r = req("~0.12");
+        assert_eq!(r.to_string(), ">=0.12.0 <0.13.0");
+        assert!(r.matches(&v("0.12.1")));
+        assert!(!r.matches(&v("0.13.0")));
+        assert_eq!(req("~0.12.1").to_string(), ">=0.12.1 <0.13.0");
+    }
+
+    #[test]
+    fn parses_keywords_and_exact_versions() {
+        assert_eq!(req("stable"), VersionReq::LatestStable);
+        assert_eq!(req("master"), VersionReq::Nightly);
+        assert_eq!(req("0.13.0"), VersionReq::Exact(v("0.13.0")));
+        assert_eq!(req(">= 0.12, < 0.14").to_string(), ">=0.12.0 <0.14.0");
+    }
+
+    #[test]
+    fn rejects_path_like_input() {
+        for s in ["../x", "..", "0.13/..", "~../x", ">=0.12 ../x", "", ">="] {
+            assert!(s.parse::<VersionReq>().is_err(), "{} should not parse", s);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+// Minimal reader for Zig Object Notation (build.zig.zon)
+
+// A parsed ZON value
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZonValue {
+    Struct(Vec<(String, ZonValue)>),
+    Tuple(Vec<ZonValue>),
+    String(String),
+    Number(String),
+    EnumLiteral(String),
+    Identifier(String),
+}
+
+impl ZonValue {
+    // Look up a field of a struct literal
+    pub fn get(&self, field: &str) -> Option<&ZonValue> {
+        match self {
+            ZonValue::Struct(fields) => fields.iter().find(|(k, _)| k == field).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ZonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Parser {
+            src: src.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn error(&self, msg: &str) -> Box<dyn std::error::Error> {
+        let line = self.src[..self.pos].iter().filter(|&&b| b == b'\n').count() + 1;
+        format!("ZON parse error on line {}: {}", line, msg).into()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    // Skip whitespace and `//` comments
+    fn skip_trivia(&mut self) {
+        while let Some(b) = self.peek() {
+            if b.is_ascii_whitespace() {
+                self.pos += 1;
+            } else if self.src[self.pos..].starts_with(b"//") {
+                while let Some(b) = self.peek() {
+                    if b == b'\n' {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Box<dyn std::error::Error>> {
+        self.skip_trivia();
+        if self.peek() != Some(byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_identifier(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        if self.src[self.pos..].starts_with(b"@\"") {
+            self.pos += 1;
+            return self.parse_string();
+        }
+
+        let start = self.pos;
+        while let Some(b) = self.peek() {
+            if b.is_ascii_alphanumeric() || b == b'_' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if start == self.pos {
+            return Err(self.error("expected identifier"));
+        }
+        Ok(String::from_utf8_lossy(&self.src[start..self.pos]).into_owned())
+    }
+
+    fn parse_string(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        self.expect(b'"')?;
+        let mut out = Vec::new();
+        loop {
+            let b = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += 1;
+            match b {
+                b'"' => break,
+                b'\n' => return Err(self.error("newline in string literal")),
+                b'\\' => {
+                    let esc = self.peek().ok_or_else(|| self.error("unterminated escape"))?;
+                    self.pos += 1;
+                    match esc {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'\\' | b'"' | b'\'' => out.push(esc),
+                        b'x' => {
+                            let hex = self
+                                .src
+                                .get(self.pos..self.pos + 2)
+                                .and_then(|h| std::str::from_utf8(h).ok())
+                                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                                .ok_or_else(|| self.error("invalid \\x escape"))?;
+                            self.pos += 2;
+                            out.push(hex);
+                        }
+                        b'u' => {
+                            self.expect(b'{')?;
+                            let start = self.pos;
+                            while self.peek().is_some_and(|b| b != b'}') {
+                                self.pos += 1;
+                            }
+                            let c = std::str::from_utf8(&self.src[start..self.pos])
+                                .ok()
+                                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid \\u escape"))?;
+                            self.expect(b'}')?;
+                            let mut buf = [0u8; 4];
+                            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                        }
+                        _ => return Err(self.error("invalid escape sequence")),
+                    }
+                }
+                _ => out.push(b),
+            }
+        }
+        String::from_utf8(out).map_err(|_| self.error("string is not valid UTF-8"))
+    }
+
+    // Consecutive `\\` lines form a single multiline string
+    fn parse_multiline_string(&mut self) -> String {
+        let mut lines = Vec::new();
+        loop {
+            self.skip_trivia();
+            if !self.src[self.pos..].starts_with(b"\\\\") {
+                break;
+            }
+            self.pos += 2;
+            let start = self.pos;
+            while self.peek().is_some_and(|b| b != b'\n') {
+                self.pos += 1;
+            }
+            let line = String::from_utf8_lossy(&self.src[start..self.pos]);
+            lines.push(line.trim_end_matches('\r').to_string());
+        }
+        lines.join("\n")
+    }
+
+    fn parse_number(&mut self) -> String {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        while let Some(b) = self.peek() {
+            if b.is_ascii_alphanumeric() || b == b'_' || b == b'.' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        String::from_utf8_lossy(&self.src[start..self.pos]).into_owned()
+    }
+
+    // Parse the body of `.{ ... }`, either struct fields or tuple items
+    fn parse_container(&mut self) -> Result<ZonValue, Box<dyn std::error::Error>> {
+        self.expect(b'{')?;
+        self.skip_trivia();
+
+        let is_struct = self.peek() == Some(b'.')
+            && self
+                .src
+                .get(self.pos + 1)
+                .is_some_and(|&b| b.is_ascii_alphabetic() || b == b'_' || b == b'@');
+        let is_struct = is_struct && {
+            // Distinguish `.name = value` from an enum literal tuple item
+            let saved = self.pos;
+            self.pos += 1;
+            let named = self.parse_identifier().is_ok() && {
+                self.skip_trivia();
+                self.peek() == Some(b'=')
+            };
+            self.pos = saved;
+            named
+        };
+
+        let mut fields = Vec::new();
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia();
+            if self.peek() == Some(b'}') {
+                self.pos += 1;
+                break;
+            }
+
+            if is_struct {
+                self.expect(b'.')?;
+                let name = self.parse_identifier()?;
+                self.expect(b'=')?;
+                fields.push((name, self.parse_value()?));
+            } else {
+                items.push(self.parse_value()?);
+            }
+
+            self.skip_trivia();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {}
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+
+        Ok(if is_struct {
+            ZonValue::Struct(fields)
+        } else {
+            ZonValue::Tuple(items)
+        })
+    }
+
+    fn parse_value(&mut self) -> Result<ZonValue, Box<dyn std::error::Error>> {
+        self.skip_trivia();
+        match self.peek() {
+            Some(b'.') => {
+                self.pos += 1;
+                if self.peek() == Some(b'{') {
+                    self.parse_container()
+                } else {
+                    Ok(ZonValue::EnumLiteral(self.parse_identifier()?))
+                }
+            }
+            Some(b'"') => Ok(ZonValue::String(self.parse_string()?)),
+            Some(b'\\') => Ok(ZonValue::String(self.parse_multiline_string())),
+            Some(b) if b.is_ascii_digit() || b == b'-' => Ok(ZonValue::Number(self.parse_number())),
+            Some(b) if b.is_ascii_alphabetic() || b == b'_' || b == b'@' => {
+                Ok(ZonValue::Identifier(self.parse_identifier()?))
+            }
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+}
+
+// Parse a ZON document
+pub fn parse(src: &str) -> Result<ZonValue, Box<dyn std::error::Error>> {
+    let mut parser = Parser::new(src);
+    let value = parser.parse_value()?;
+    parser.skip_trivia();
+    if parser.peek().is_some() {
+        return Err(parser.error("trailing characters after value"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILD_ZIG_ZON: &str = r#"// A typical package manifest
+.{
+    .name = .example,
+    .version = "0.1.0",
+    .fingerprint = 0x9c2a1b3d4e5f6071, // generated by zig init
+    .minimum_zig_version = "0.14.0-dev.1234+abcdef123",
+    .dependencies = .{
+        .zigimg = .{
+            .url = "https://example.com/zigimg.tar.gz",
+            .hash = "1220abcdef",
+            .lazy = true,
+        },
+        // Nothing yet
+        .empty = .{},
+    },
+    .paths = .{
+        "build.zig",
+        "build.zig.zon",
+        "src",
+    },
+    .description =
+        \\A package with
+        \\  a multiline description
+    ,
+}
+"#;
+
+    #[test]
+    fn parses_a_build_zig_zon() {
+        let zon = parse(BUILD_ZIG_ZON).unwrap();
+        assert_eq!(zon.get("name"), Some(&ZonValue::EnumLiteral("example".to_string())));
+        assert_eq!(zon.get("version").and_then(ZonValue::as_str), Some("0.1.0"));
+        assert_eq!(
+            zon.get("minimum_zig_version").and_then(ZonValue::as_str),
+            Some("0.14.0-dev.1234+abcdef123")
+        );
+        assert_eq!(zon.get("fingerprint"), Some(&ZonValue::Number("0x9c2a1b3d4e5f6071".to_string())));
+
+        let dependencies = zon.get("dependencies").unwrap();
+        let zigimg = dependencies.get("zigimg").unwrap();
+        assert_eq!(zigimg.get("hash").and_then(ZonValue::as_str), Some("1220abcdef"));
+        assert_eq!(zigimg.get("lazy"), Some(&ZonValue::Identifier("true".to_string())));
+        assert_eq!(dependencies.get("empty"), Some(&ZonValue::Tuple(Vec::new())));
+
+        let Some(ZonValue::Tuple(paths)) = zon.get("paths") else {
+            panic!("paths should be a tuple");
+        };
+        assert_eq!(paths.len(), 3);
+        assert_eq!(
+            zon.get("description").and_then(ZonValue::as_str),
+            Some("A package with\n  a multiline description")
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for src in ["", ".{ .name = }", ".{ .a = 1 .b = 2 }", ".{ \"unterminated }", ".{} trailing"] {
+            assert!(parse(src).is_err(), "{:?} should not parse", src);
+        }
+    }
+}